@@ -0,0 +1,61 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use logging_timer::{DurationFormat, finish, set_duration_format, timer};
+use std::fmt::Write;
+use std::time::Instant;
+
+/// Compares the cost of logging timer messages with each of the duration formats.
+/// The logger formats every message into a buffer and then throws it away, so the
+/// time measured is that of producing the message text, as a real logger would.
+///
+/// To run, do:
+///     cargo run --release --example duration_format_bench
+fn main() {
+    log::set_logger(&SinkLogger).unwrap();
+    log::set_max_level(LevelFilter::Info);
+
+    const MESSAGES: u32 = 1_000_000;
+    for (label, format) in [
+        ("Debug", DurationFormat::Debug),
+        ("Ascii", DurationFormat::Ascii),
+        ("Nanos", DurationFormat::Nanos),
+        ("Millis", DurationFormat::Millis),
+    ] {
+        set_duration_format(format);
+        let start = Instant::now();
+        for _ in 0..MESSAGES {
+            let tmr = timer!(Level::Info; "BENCH");
+            finish!(tmr);
+        }
+        let elapsed = start.elapsed();
+        println!(
+            "{:>6}: {:?} for {} messages, {} ns/message",
+            label,
+            elapsed,
+            MESSAGES,
+            elapsed.as_nanos() / MESSAGES as u128
+        );
+    }
+}
+
+struct SinkLogger;
+
+impl Log for SinkLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Info
+    }
+
+    fn log(&self, record: &Record) {
+        thread_local! {
+            static BUFFER: std::cell::RefCell<String> = const { std::cell::RefCell::new(String::new()) };
+        }
+
+        BUFFER.with(|buffer| {
+            let mut buffer = buffer.borrow_mut();
+            buffer.clear();
+            write!(buffer, "{} [{}] {}", record.level(), record.target(), record.args()).unwrap();
+            std::hint::black_box(buffer.as_str());
+        });
+    }
+
+    fn flush(&self) {}
+}
@@ -3,7 +3,7 @@
 
 use chrono::{DateTime, Utc};
 use env_logger::Builder;
-use logging_timer::{executing, finish, stime, stimer, time, timer};
+use logging_timer::{executing, finish, phase, stime, stimer, time, timer};
 use std::{default, io::Write, time::Duration};
 use tokio::*;
 
@@ -96,6 +96,9 @@ async fn main() {
     execute_and_finish_without_args();
     println!("");
 
+    stimer_with_phases();
+    println!("");
+
     executed_by_async().await;
     println!("");
 
@@ -241,6 +244,16 @@ fn execute_and_finish_without_args() {
     finish!(tmr);
 }
 
+fn stimer_with_phases() {
+    let tmr = stimer!("WITH_PHASES");
+    {
+        let _p = phase!(tmr, "PARSE");
+    }
+    {
+        let _p = phase!(tmr, "VALIDATE");
+    }
+}
+
 trait AsyncFoo {
     async fn foo(&self);
 }
@@ -0,0 +1,58 @@
+use logging_timer::{Clock, executing, set_clock, set_fallback_to_stderr, stimer};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Demonstrates timers measuring simulated time in a discrete-event simulation.
+/// The simulation advances logical time itself, so the elapsed times reported by
+/// the timers are exact and the same on every run, however fast the machine is.
+///
+/// To run, do:
+///     cargo run --example simulation_clock
+fn main() {
+    // No logger is configured, so just send the timer output to stderr.
+    set_fallback_to_stderr(true);
+
+    let clock = Arc::new(SimulationClock::new());
+    set_clock(clock.clone()).unwrap();
+
+    let mut events = vec![
+        Event { at: Duration::from_millis(250), name: "ARRIVAL" },
+        Event { at: Duration::from_millis(1000), name: "SERVICE" },
+        Event { at: Duration::from_millis(1750), name: "DEPARTURE" },
+    ];
+    events.reverse();
+
+    let tmr = stimer!("SIMULATION");
+    while let Some(event) = events.pop() {
+        clock.advance_to(event.at);
+        executing!(tmr, "Processed {}", event.name);
+    }
+} // Logs "SIMULATION, Elapsed=1.75s"
+
+struct Event {
+    at: Duration,
+    name: &'static str,
+}
+
+/// A clock whose time only moves when the simulation says so.
+struct SimulationClock {
+    epoch: Instant,
+    now: Mutex<Duration>,
+}
+
+impl SimulationClock {
+    fn new() -> Self {
+        SimulationClock { epoch: Instant::now(), now: Mutex::new(Duration::ZERO) }
+    }
+
+    /// Advances simulated time to `at`, measured from the start of the simulation.
+    fn advance_to(&self, at: Duration) {
+        *self.now.lock().unwrap() = at;
+    }
+}
+
+impl Clock for SimulationClock {
+    fn now(&self) -> Instant {
+        self.epoch + *self.now.lock().unwrap()
+    }
+}
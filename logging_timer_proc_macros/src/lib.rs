@@ -1,14 +1,30 @@
+use syn::visit_mut::VisitMut;
 use syn::{Expr, Stmt, WherePredicate};
 
 #[macro_use]
 extern crate quote;
-#[macro_use]
-extern crate syn;
 extern crate proc_macro2;
+extern crate syn;
 
 const DEFAULT_LEVEL: &str = "debug";
 const DEFAULT_NAME_PATTERN: &str = "{}";
 
+/// The parsed arguments of the `time` and `stime` attributes.
+struct MacroArgs {
+    level: String,
+    name_pattern: String,
+    /// Generic type parameters whose monomorphized type names are included in the timer name.
+    types: Vec<syn::Type>,
+    /// The log level that a lone argument, taken as the name pattern, looks like a misspelling of.
+    misspelled_level: Option<&'static str>,
+    /// True if the function returns a `Result` and errors are to be included in the message.
+    result: bool,
+    /// True if the length of the returned value is to be included in the message.
+    result_len: bool,
+    /// The level to log the 'finished' message at if the function returns an error.
+    on_err: Option<String>,
+}
+
 fn extract_literal(token_tree: &proc_macro::TokenTree) -> String {
     let s = match token_tree {
         proc_macro::TokenTree::Literal(literal) => literal.to_string(),
@@ -22,21 +38,145 @@ fn extract_literal(token_tree: &proc_macro::TokenTree) -> String {
     s
 }
 
+// The arguments are a comma-separated list. The level and name pattern are given as plain
+// string literals, everything else is given as `key = "value"`.
+fn get_macro_args(metadata: proc_macro::TokenStream) -> MacroArgs {
+    let tokens: Vec<proc_macro::TokenTree> = metadata.into_iter().collect();
+    let mut literals = Vec::new();
+    let mut types = Vec::new();
+    let mut result = false;
+    let mut result_len = false;
+    let mut on_err = None;
+
+    for arg in tokens.split(|token| is_punct(token, ',')) {
+        match arg {
+            [] => {}
+            [literal @ proc_macro::TokenTree::Literal(_)] => literals.push(literal.clone()),
+            [proc_macro::TokenTree::Ident(flag)] => match flag.to_string().as_str() {
+                "result" => result = true,
+                "result_len" => result_len = true,
+                other => {
+                    panic!("Unknown argument `{}`. Valid flags are: result, result_len", other)
+                }
+            },
+            [proc_macro::TokenTree::Ident(key), eq, value @ proc_macro::TokenTree::Literal(_)]
+                if is_punct(eq, '=') =>
+            {
+                match key.to_string().as_str() {
+                    "types" => types = parse_types(&extract_literal(value)),
+                    "on_err" => {
+                        let level = extract_literal(value).to_ascii_lowercase();
+                        if !LEVELS[..5].contains(&level.as_str()) {
+                            panic!(
+                                "Invalid `on_err` level \"{}\". Valid values are: error, warn, info, debug, trace",
+                                level
+                            );
+                        }
+                        on_err = Some(level);
+                    }
+                    other => panic!(
+                        "Unknown argument `{}`. Valid named arguments are: types, on_err",
+                        other
+                    ),
+                }
+            }
+            _ => panic!(
+                "Invalid argument. Specify the log level and name pattern as string literals, and named arguments as `name = \"value\"`."
+            ),
+        }
+    }
+
+    let misspelled_level = match literals.as_slice() {
+        [only] => likely_misspelled_level(&extract_literal(only)),
+        _ => None,
+    };
+
+    let (level, name_pattern) = get_log_level_and_name_pattern(literals);
+    // Escalating the level on error implies inspecting the result.
+    let result = result || on_err.is_some();
+    MacroArgs { level, name_pattern, types, misspelled_level, result, result_len, on_err }
+}
+
+const LEVELS: [&str; 5] = ["error", "warn", "info", "debug", "trace"];
+
+// A lone argument is taken as the name pattern if it is not a log level, which means a
+// misspelled level such as "infoo" silently becomes the timer name. Guess whether that has
+// happened: the argument must be a single lower-case word, as levels are, within a small
+// edit distance of a level. Upper-case words such as "ERRORS" are the usual timer names.
+fn likely_misspelled_level(arg: &str) -> Option<&'static str> {
+    if arg.is_empty() || !arg.chars().all(|c| c.is_ascii_lowercase()) {
+        return None;
+    }
+
+    LEVELS.iter().copied().find(|level| {
+        let distance = edit_distance(arg, level);
+        distance == 1 || (distance == 2 && level.len() >= 5)
+    })
+}
+
+// The Levenshtein distance between two short ASCII strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.as_bytes();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.bytes().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == *cb { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+// Compiler warnings cannot be emitted by a proc-macro on stable Rust, so use of a
+// deprecated constant is generated instead; its note is shown as the warning.
+fn misspelled_level_warning(arg: &str, level: &str) -> proc_macro2::TokenStream {
+    let message = format!(
+        "\"{}\" is being used as the timer name, did you mean the log level \"{}\"? \
+         If the name is intended, specify the level too, e.g. #[time(\"debug\", \"{}\")]",
+        arg, level, arg
+    );
+
+    if cfg!(feature = "strict-levels") {
+        panic!("{}", message);
+    }
+
+    quote! {
+        {
+            #[deprecated(note = #message)]
+            #[allow(non_upper_case_globals)]
+            const possibly_misspelled_log_level: () = ();
+            #[allow(clippy::let_unit_value)]
+            let _ = possibly_misspelled_log_level;
+        }
+    }
+}
+
+fn is_punct(token: &proc_macro::TokenTree, c: char) -> bool {
+    match token {
+        proc_macro::TokenTree::Punct(punct) => punct.as_char() == c,
+        _ => false,
+    }
+}
+
+// `types = "T, U"` names the generic parameters to include in the timer name.
+fn parse_types(types: &str) -> Vec<syn::Type> {
+    types
+        .split(',')
+        .map(str::trim)
+        .filter(|ty| !ty.is_empty())
+        .map(|ty| {
+            syn::parse_str(ty).unwrap_or_else(|_| panic!("Invalid type `{}` in `types`", ty))
+        })
+        .collect()
+}
+
 // log::LogLevel can be Error, Warn, Info, Debug, Trace.
 // Debug is the default if nothing is specified.
 // We also allow 'Never' to mean disable timer instrumentation
 // altogether. Any casing is allowed.
-fn get_log_level_and_name_pattern(metadata: proc_macro::TokenStream) -> (String, String) {
-    // Grab everything into a vector and filter out any intervening punctuation
-    // (commas come through as TokenTree::Punct(_)).
-    let macro_args: Vec<proc_macro::TokenTree> = metadata
-        .into_iter()
-        .filter(|token| match token {
-            proc_macro::TokenTree::Literal(_) => true,
-            _ => false,
-        })
-        .collect();
-
+fn get_log_level_and_name_pattern(macro_args: Vec<proc_macro::TokenTree>) -> (String, String) {
     if macro_args.is_empty() {
         return (DEFAULT_LEVEL.to_string(), DEFAULT_NAME_PATTERN.to_string());
     }
@@ -47,7 +187,7 @@ fn get_log_level_and_name_pattern(metadata: proc_macro::TokenStream) -> (String,
 
     let first_arg = extract_literal(&macro_args[0]);
 
-    if first_arg.contains("{}") && macro_args.len() == 2 {
+    if (first_arg.contains("{}") || first_arg.contains("{fn}")) && macro_args.len() == 2 {
         panic!(
             "Invalid first argument. Specify the log level as the first argument and the pattern as the second."
         );
@@ -93,44 +233,174 @@ fn get_timer_name(name_pattern: &str, fn_name: &str) -> String {
     timer_name
 }
 
-/// Instruments the function with a `timer!`, which logs a message at the end of function
-/// execution stating the elapsed time.
-///
-/// The attribute accepts two string literals as arguments. The first is the log level,
-/// valid values of which are "error", "warn", "info", "debug", "trace" or "never".
-/// The default value is "debug". "never" can be used to temporarily disable instrumentation
-/// of the function without deleting the attribute.
-///
-/// The second argument is the function name pattern. The pattern is helpful to
-/// disambiguate functions when you have many functions in the same module with the same
-/// name: `new` might occur many times on different structs, for example. In the pattern,
-/// "{}" will be replaced with the name of the function.
-///
-/// Examples:
-///     #[time]                                 // Use default log level of Debug
-///     #[time("info")]                         // Set custom log level
-///     #[time("info", "FirstStruct::{}")]      // Logs "FirstStruct::new()" at Info
-///     #[time("info", "SecondStruct::{}")]     // Logs "SecondStruct::new()" at Info
-///     #[time("ThirdStruct::{}")]              // Logs "ThirdStruct::new()" at Debug
-///     #[time("never")]                        // Turn off instrumentation at compile time
-#[proc_macro_attribute]
-pub fn time(
+// `{fn}` is a more self-documenting way of writing `{}`.
+fn normalize_pattern(name_pattern: &str) -> String {
+    name_pattern.replacen("{fn}", "{}", 1)
+}
+
+// Splits a piece of the timer name at each `{module}` placeholder, giving a list of
+// string literals separated by `module_path!()`, which is expanded in the user's crate
+// to the path of the module containing the function.
+fn module_pieces(s: &str) -> Vec<proc_macro2::TokenStream> {
+    let mut pieces = Vec::new();
+    for (i, piece) in s.split("{module}").enumerate() {
+        if i > 0 {
+            pieces.push(quote! { module_path!() });
+        }
+        if !piece.is_empty() {
+            pieces.push(quote! { #piece });
+        }
+    }
+    pieces
+}
+
+// Builds an expression that evaluates to the timer name at runtime, with the monomorphized
+// names of the generic `types` inserted after the function name, e.g. `parse::<u32>()`.
+// The pieces are concatenated from an array of `&str` to avoid any escaping issues that
+// building a format string from the pattern would have.
+fn get_generic_timer_name(
+    name_pattern: &str,
+    fn_name: &str,
+    types: &[syn::Type],
+) -> proc_macro2::TokenStream {
+    let type_names = types.iter().enumerate().map(|(i, ty)| {
+        let separator = if i == 0 { "" } else { ", " };
+        quote! { #separator, ::std::any::type_name::<#ty>() }
+    });
+
+    let (prefix, suffix, fn_part, open, close) = match name_pattern.find("{}") {
+        Some(idx) => (&name_pattern[..idx], &name_pattern[idx + 2..], fn_name, "::<", ">()"),
+        None => (name_pattern, "", "", "<", ">"),
+    };
+    let prefix = module_pieces(prefix);
+    let suffix = module_pieces(suffix);
+
+    quote! {
+        [#(#prefix,)* #fn_part, #open, #(#type_names,)* #close, #(#suffix,)*].concat()
+    }
+}
+
+fn level_tokens(level: &str) -> proc_macro2::TokenStream {
+    match level {
+        "error" => quote! { ::logging_timer::Level::Error },
+        "warn" => quote! { ::logging_timer::Level::Warn },
+        "info" => quote! { ::logging_timer::Level::Info  },
+        "debug" => quote! { ::logging_timer::Level::Debug  },
+        "trace" => quote! { ::logging_timer::Level::Trace  },
+        _ => panic!("Unrecognized log level: {}", level),
+    }
+}
+
+// Creates the timer. With `on_err`, if the timer's own level is disabled it is created at
+// the error level instead, so that an error can still be reported, but keeps its own
+// level so that nothing is logged on success.
+fn create_timer(
+    timer_macro: &proc_macro2::TokenStream,
+    log_level: &proc_macro2::TokenStream,
+    on_err: Option<&proc_macro2::TokenStream>,
+    timer_name: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    match on_err {
+        Some(on_err) => quote! {
+            let _tmr = ::logging_timer::#timer_macro!(#log_level; #timer_name).or_else(|| {
+                ::logging_timer::timer!(#on_err; #timer_name).map(|t| t.__with_level(#log_level))
+            });
+        },
+        None => quote! {
+            let _tmr = ::logging_timer::#timer_macro!(#log_level; #timer_name);
+        },
+    }
+}
+
+// Rewrites a function body to be the value of the block labelled `'__logging_timer`, so
+// that the result can be inspected before it is returned: `return` becomes a `break` out
+// of the block, and `?` a `break` with the converted error. Closures, async blocks and
+// nested items have their own `return` and `?`, so they are left alone. A `return` hidden
+// inside a macro call cannot be seen, and still leaves the function directly.
+struct BreakOnReturn;
+
+impl VisitMut for BreakOnReturn {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Closure(_) | Expr::Async(_) => return,
+            Expr::Return(ret) => {
+                let value = match ret.expr.as_mut() {
+                    Some(value) => {
+                        self.visit_expr_mut(value);
+                        quote! { #value }
+                    }
+                    None => quote! { () },
+                };
+                *expr = syn::parse_quote! { break '__logging_timer #value };
+                return;
+            }
+            Expr::Try(try_expr) => {
+                self.visit_expr_mut(&mut try_expr.expr);
+                let value = &try_expr.expr;
+                *expr = syn::parse_quote! {
+                    match #value {
+                        ::std::result::Result::Ok(value) => value,
+                        ::std::result::Result::Err(err) => break '__logging_timer
+                            ::std::result::Result::Err(::std::convert::From::from(err)),
+                    }
+                };
+                return;
+            }
+            _ => {}
+        }
+        syn::visit_mut::visit_expr_mut(self, expr);
+    }
+
+    fn visit_item_mut(&mut self, _item: &mut syn::Item) {}
+}
+
+// Replaces each `impl Trait` in a type with `_`, so that it can annotate a local.
+struct InferImplTrait;
+
+impl VisitMut for InferImplTrait {
+    fn visit_type_mut(&mut self, ty: &mut syn::Type) {
+        if let syn::Type::ImplTrait(_) = ty {
+            *ty = syn::Type::Infer(syn::TypeInfer { underscore_token: Default::default() });
+        } else {
+            syn::visit_mut::visit_type_mut(self, ty);
+        }
+    }
+}
+
+// Shared implementation of the `time` and `stime` attributes. The only difference
+// between them is the timer macro that is used to instrument the function.
+fn instrument(
     metadata: proc_macro::TokenStream,
     input: proc_macro::TokenStream,
+    timer_macro: proc_macro2::TokenStream,
 ) -> proc_macro::TokenStream {
-    let (level, name_pattern) = get_log_level_and_name_pattern(metadata);
+    let args = get_macro_args(metadata);
+    let level = args.level;
 
     if level != "never" {
-        let input_fn: syn::ItemFn = parse_macro_input!(input as syn::ItemFn);
+        // Free functions, methods in impls and default methods in traits all parse as an
+        // `ItemFn`. A trait method without a default body does not, and has nothing to time.
+        let input_fn: syn::ItemFn = match syn::parse(input.clone()) {
+            Ok(input_fn) => input_fn,
+            Err(err) => {
+                return match syn::parse::<syn::TraitItemMethod>(input) {
+                    Ok(method) if method.default.is_none() => syn::Error::new_spanned(
+                        method.sig,
+                        "#[time] and #[stime] need a function body; put them on the \
+                         implementations of a trait method that has no default body",
+                    )
+                    .to_compile_error()
+                    .into(),
+                    _ => err.to_compile_error().into(),
+                };
+            }
+        };
         let attrs = input_fn.attrs;
         let visibility = input_fn.vis;
-        let ident = input_fn.sig.ident;
-        let asyncness = input_fn.sig.asyncness;
-        let unsafety = input_fn.sig.unsafety;
-        let inputs = input_fn.sig.inputs;
-        let output = input_fn.sig.output;
-        let generics = &input_fn.sig.generics;
-        let where_clause = &input_fn.sig.generics.where_clause;
+        let sig = &input_fn.sig;
+        let ident = &sig.ident;
+        let output = &sig.output;
+        let where_clause = &sig.generics.where_clause;
         let block = input_fn.block;
         let is_async_trait = if let Some(w) = where_clause {
             w.predicates.iter().any(|x| match x {
@@ -143,18 +413,105 @@ pub fn time(
             false
         };
 
-        let timer_name = get_timer_name(&name_pattern, &ident.to_string());
+        let log_level = level_tokens(&level);
+        let on_err = args.on_err.as_deref().map(level_tokens);
 
-        let log_level = match level.as_str() {
-            "error" => quote! { ::logging_timer::Level::Error },
-            "warn" => quote! { ::logging_timer::Level::Warn },
-            "info" => quote! { ::logging_timer::Level::Info  },
-            "debug" => quote! { ::logging_timer::Level::Debug  },
-            "trace" => quote! { ::logging_timer::Level::Trace  },
-            _ => panic!("Unrecognized log level: {}", level),
+        let warning = match args.misspelled_level {
+            Some(misspelled) => Some(misspelled_level_warning(&args.name_pattern, misspelled)),
+            None => None,
         };
 
-        let block = if is_async_trait {
+        let name_pattern = normalize_pattern(&args.name_pattern);
+        let timer_stmts = if args.types.is_empty() {
+            let timer_name = get_timer_name(&name_pattern, &ident.to_string());
+            let timer_name = if timer_name.contains("{module}") {
+                let pieces = module_pieces(&timer_name);
+                quote! { concat!(#(#pieces),*) }
+            } else {
+                quote! { #timer_name }
+            };
+            let create = create_timer(&timer_macro, &log_level, on_err.as_ref(), &timer_name);
+            quote! {
+                #warning
+                #create
+            }
+        } else {
+            // The name is only known at runtime, so it has to be built into a local that
+            // outlives the timer. Avoid building it at all when the timer is disabled.
+            let timer_name =
+                get_generic_timer_name(&name_pattern, &ident.to_string(), &args.types);
+            let enabled = match &on_err {
+                Some(on_err) => quote! {
+                    ::logging_timer::LoggingTimer::is_enabled(#log_level)
+                        || ::logging_timer::LoggingTimer::is_enabled(#on_err)
+                },
+                None => quote! { ::logging_timer::LoggingTimer::is_enabled(#log_level) },
+            };
+            let create = create_timer(
+                &timer_macro,
+                &log_level,
+                on_err.as_ref(),
+                &quote! { __logging_timer_name.as_str() },
+            );
+            quote! {
+                #warning
+                let __logging_timer_name = if #enabled {
+                    #timer_name
+                } else {
+                    ::std::string::String::new()
+                };
+                #create
+            }
+        };
+
+        let block = if args.result || args.result_len {
+            if is_async_trait {
+                panic!("The `result`, `result_len` and `on_err` arguments are not supported with async_trait");
+            }
+            let result_type = match output {
+                syn::ReturnType::Type(_, ty) => ty,
+                syn::ReturnType::Default if args.result => {
+                    panic!("The `result` and `on_err` arguments require the function to return a Result")
+                }
+                syn::ReturnType::Default => {
+                    panic!("The `result_len` argument requires the function to return a value with a `len` method")
+                }
+            };
+            // With `result` too, the length is that of the `Ok` value.
+            let finish_len = match (args.result_len, args.result) {
+                (true, true) => quote! {
+                    if let Ok(value) = &__logging_timer_result {
+                        ::logging_timer::__finish_len(&_tmr, value.len());
+                    }
+                },
+                (true, false) => quote! {
+                    ::logging_timer::__finish_len(&_tmr, __logging_timer_result.len());
+                },
+                (false, _) => quote! {},
+            };
+            let finish_result = match (args.result, &on_err) {
+                (true, Some(on_err)) => quote! {
+                    ::logging_timer::__finish_result(&_tmr, &__logging_timer_result, Some(#on_err));
+                },
+                (true, None) => quote! {
+                    ::logging_timer::__finish_result(&_tmr, &__logging_timer_result, None);
+                },
+                (false, _) => quote! {},
+            };
+            let mut result_type = (**result_type).clone();
+            InferImplTrait.visit_type_mut(&mut result_type);
+            let mut block = block.clone();
+            BreakOnReturn.visit_block_mut(&mut block);
+            quote! {
+                {
+                    #timer_stmts
+                    let __logging_timer_result: #result_type = '__logging_timer: #block;
+                    #finish_len
+                    #finish_result
+                    __logging_timer_result
+                }
+            }
+        } else if is_async_trait {
             let modified_block: Vec<_> = block
                 .stmts
                 .iter()
@@ -168,7 +525,7 @@ pub fn time(
                                 let stmts = &block.stmts;
                                 quote! {
                                     async #capture {
-                                        let _tmr = ::logging_timer::timer!(#log_level; #timer_name);
+                                        #timer_stmts
                                         #(#stmts)*
                                     }
                                 }
@@ -191,13 +548,13 @@ pub fn time(
             }
         } else {
             quote! {
-                let _tmr = ::logging_timer::timer!(#log_level; #timer_name);
+                #timer_stmts
                 #block
             }
         };
 
         (quote!(
-            #(#attrs)* #visibility #unsafety #asyncness fn #ident #generics (#inputs) #output #where_clause {
+            #(#attrs)* #visibility #sig {
                 #block
             }
         ))
@@ -207,7 +564,59 @@ pub fn time(
     }
 }
 
-// TODO: Get rid of this copy-paste. The only difference is the timer type.
+/// Instruments the function with a `timer!`, which logs a message at the end of function
+/// execution stating the elapsed time.
+///
+/// The attribute accepts two string literals as arguments. The first is the log level,
+/// valid values of which are "error", "warn", "info", "debug", "trace" or "never".
+/// The default value is "debug". "never" can be used to temporarily disable instrumentation
+/// of the function without deleting the attribute.
+///
+/// The second argument is the function name pattern. The pattern is helpful to
+/// disambiguate functions when you have many functions in the same module with the same
+/// name: `new` might occur many times on different structs, for example. In the pattern,
+/// "{}" will be replaced with the name of the function. "{fn}" may be used instead of
+/// "{}", and "{module}" is replaced with the path of the module containing the function.
+///
+/// For generic functions, the named argument `types` lists generic type parameters
+/// whose monomorphized type names (from `std::any::type_name`) are included in the
+/// timer name, so that each instantiation of the function is timed distinctly.
+///
+/// For functions that return a `Result`, the flag `result` adds the error's `Debug`
+/// representation to the 'finished' message when the function fails, and the named argument
+/// `on_err` gives a level to log that message at instead, so that a function can be quiet
+/// on success but loud on failure. `on_err` implies `result`. The error must implement
+/// `Debug`. These arguments are not supported with `async_trait`.
+///
+/// For functions that return a collection, the flag `result_len` adds `Len=N` to the
+/// 'finished' message, where `N` is the result of calling `len()` on the returned value,
+/// so that the size of the result is logged without its contents. Combined with `result`,
+/// the length is that of the `Ok` value. The returned type must have a `len` method.
+///
+/// A lone argument which is not a log level but looks like a misspelling of one, such as
+/// "infoo", causes a compiler warning, since it is taken as the name pattern. Specify both
+/// arguments to name a timer like that deliberately. With the `strict-levels` feature of
+/// `logging_timer` the warning becomes an error.
+///
+/// Examples:
+///     #[time]                                 // Use default log level of Debug
+///     #[time("info")]                         // Set custom log level
+///     #[time("info", "FirstStruct::{}")]      // Logs "FirstStruct::new()" at Info
+///     #[time("info", "SecondStruct::{}")]     // Logs "SecondStruct::new()" at Info
+///     #[time("ThirdStruct::{}")]              // Logs "ThirdStruct::new()" at Debug
+///     #[time("{module}::{fn}")]               // Logs "my_crate::parser::new()" at Debug
+///     #[time("never")]                        // Turn off instrumentation at compile time
+///     #[time(types = "T")]                    // Logs "parse::<u32>()" for parse::<u32>
+///     #[time("info", types = "K, V")]         // Logs "insert::<u32, alloc::string::String>()"
+///     #[time("trace", on_err = "warn")]       // Logs at Trace, or at Warn with "Error=..." on Err
+///     #[time(result_len)]                     // Logs "load_rows(), Elapsed=..., Len=1500"
+#[proc_macro_attribute]
+pub fn time(
+    metadata: proc_macro::TokenStream,
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    instrument(metadata, input, quote! { timer })
+}
 
 /// Instruments the function with an `stimer!`, which logs two messages, one at the start
 /// of the function and one at the end of execution stating the elapsed time.
@@ -220,7 +629,17 @@ pub fn time(
 /// The second argument is the function name pattern. The pattern is helpful to
 /// disambiguate functions when you have many functions in the same module with the same
 /// name: `new` might occur many times on different structs, for example. In the pattern,
-/// "{}" will be replaced with the name of the function.
+/// "{}" will be replaced with the name of the function. "{fn}" may be used instead of
+/// "{}", and "{module}" is replaced with the path of the module containing the function.
+///
+/// For generic functions, the named argument `types` lists generic type parameters
+/// whose monomorphized type names (from `std::any::type_name`) are included in the
+/// timer name, so that each instantiation of the function is timed distinctly.
+///
+/// The `result`, `result_len` and `on_err` arguments work as for `time`.
+///
+/// As with `time`, a lone argument that looks like a misspelled log level causes a
+/// compiler warning, or an error with the `strict-levels` feature.
 ///
 /// Examples:
 ///     #[stime]                                 // Use default log level of Debug
@@ -228,45 +647,15 @@ pub fn time(
 ///     #[stime("info", "FirstStruct::{}")]      // Logs "FirstStruct::new()" at Info
 ///     #[stime("info", "SecondStruct::{}")]     // Logs "SecondStruct::new()" at Info
 ///     #[stime("ThirdStruct::{}")]              // Logs "ThirdStruct::new()" at Debug
+///     #[stime("{module}::{fn}")]               // Logs "my_crate::parser::new()" at Debug
 ///     #[stime("never")]                        // Turn off instrumentation at compile time
+///     #[stime(types = "T")]                    // Logs "parse::<u32>()" for parse::<u32>
+///     #[stime("info", types = "K, V")]         // Logs "insert::<u32, alloc::string::String>()"
+///     #[stime("trace", on_err = "warn")]       // Logs at Trace, or at Warn with "Error=..." on Err
 #[proc_macro_attribute]
 pub fn stime(
     metadata: proc_macro::TokenStream,
     input: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
-    let (level, name_pattern) = get_log_level_and_name_pattern(metadata);
-
-    if level != "never" {
-        let input_fn: syn::ItemFn = parse_macro_input!(input as syn::ItemFn);
-        let attrs = input_fn.attrs;
-        let visibility = input_fn.vis;
-        let ident = input_fn.sig.ident;
-        let asyncness = input_fn.sig.asyncness;
-        let inputs = input_fn.sig.inputs;
-        let output = input_fn.sig.output;
-        let generics = &input_fn.sig.generics;
-        let where_clause = &input_fn.sig.generics.where_clause;
-        let block = input_fn.block;
-
-        let timer_name = get_timer_name(&name_pattern, &ident.to_string());
-
-        let log_level = match level.as_str() {
-            "error" => quote! { ::logging_timer::Level::Error },
-            "warn" => quote! { ::logging_timer::Level::Warn },
-            "info" => quote! { ::logging_timer::Level::Info  },
-            "debug" => quote! { ::logging_timer::Level::Debug  },
-            "trace" => quote! { ::logging_timer::Level::Trace  },
-            _ => panic!("Unrecognized log level: {}", level),
-        };
-
-        (quote!(
-            #(#attrs)* #visibility #asyncness fn #ident #generics (#inputs) #output #where_clause {
-                let _tmr = ::logging_timer::stimer!(#log_level; #timer_name);
-                #block
-            }
-        ))
-        .into()
-    } else {
-        proc_macro::TokenStream::from(input).into()
-    }
+    instrument(metadata, input, quote! { stimer })
 }
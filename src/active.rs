@@ -0,0 +1,119 @@
+use crate::deadline::Location;
+use crate::{TimerName, TimerTarget, clock, format};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::Instant;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A timer which has been created but has not yet finished, on any thread.
+struct ActiveTimer {
+    name: String,
+    level: ::log::Level,
+    extra_info: Option<String>,
+    start_time: Instant,
+    location: Location,
+}
+
+fn registry() -> MutexGuard<'static, HashMap<u64, ActiveTimer>> {
+    static REGISTRY: OnceLock<Mutex<HashMap<u64, ActiveTimer>>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Turns on the tracking of all the timers that are running, on every thread, so that
+/// `finish_all_active` can report the ones that never finish. It costs a lock and an
+/// allocation for each timer, so the default is off. Only timers created while this is
+/// on are tracked.
+pub fn enable_active_timer_tracking(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+#[inline]
+pub(crate) fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Records that a timer has started, returning its id.
+pub(crate) fn register(
+    name: String,
+    level: ::log::Level,
+    extra_info: Option<String>,
+    start_time: Instant,
+    location: Location,
+) -> u64 {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    registry().insert(id, ActiveTimer { name, level, extra_info, start_time, location });
+    id
+}
+
+/// Records that a timer has finished. Returns false if the timer is no longer
+/// registered because it has already been reported as abandoned.
+pub(crate) fn unregister(id: u64) -> bool {
+    registry().remove(&id).is_some()
+}
+
+/// Records that a timer has been renamed.
+pub(crate) fn rename(id: u64, name: String) {
+    if let Some(timer) = registry().get_mut(&id) {
+        timer.name = name;
+    }
+}
+
+/// Logs a message with a target of `TimerAbandoned` for every tracked timer that is still
+/// running, in the order they started, then forgets them so that they log nothing when
+/// they do eventually finish. This surfaces timers that were leaked, for example by being
+/// stored in a static, or whose scope never ended. Rust does not run destructors at exit,
+/// so call this at the end of `main`. The tracking must have been turned on first, see
+/// `enable_active_timer_tracking`.
+///
+/// ```norun
+/// fn main() {
+///     logging_timer::enable_active_timer_tracking(true);
+///     run();
+///     logging_timer::finish_all_active();
+/// }
+/// ```
+pub fn finish_all_active() {
+    let mut abandoned: Vec<ActiveTimer> = registry().drain().map(|(_, timer)| timer).collect();
+    abandoned.sort_by_key(|timer| timer.start_time);
+
+    for timer in abandoned {
+        if !(::log::log_enabled!(timer.level) || crate::stderr::fallback_active()) {
+            continue;
+        }
+
+        let message = format::Message {
+            target: TimerTarget::Abandoned,
+            file: timer.location.file,
+            line: timer.location.line,
+            name: &TimerName::Str(&timer.name),
+            operation_id: None,
+            thread_label: None,
+            tracing_span: None,
+            span: None,
+            elapsed: Some(clock::elapsed_since(timer.start_time)),
+            request_elapsed: None,
+            duration_format: None,
+            parent_fraction: None,
+            wall_clock: None,
+            cpu: None,
+            queue: None,
+            phases: None,
+            extra_info: timer.extra_info.as_deref(),
+            args: None,
+        };
+
+        crate::emit(
+            &::log::RecordBuilder::new()
+                .level(timer.level)
+                .target(format::target(TimerTarget::Abandoned))
+                .file(Some(timer.location.file))
+                .module_path(timer.location.module_path)
+                .line(Some(timer.location.line))
+                .args(format_args!("{}", message))
+                .build(),
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use crate::TimerName;
+use crate::stats::{self, TimerStats};
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+struct Scope {
+    id: usize,
+    name: String,
+    stats: Option<TimerStats>,
+}
+
+thread_local! {
+    // The aggregation scopes currently alive on this thread, innermost last.
+    static SCOPES: RefCell<Vec<Scope>> = const { RefCell::new(Vec::new()) };
+}
+
+// The number of scopes alive on all threads, so that the common case of there being
+// none at all costs a single atomic load. Also used to give each scope an id.
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// While a value of this type is alive, every timer with the given name that finishes on
+/// the current thread is aggregated, and when it is dropped a single `TimerStats` message
+/// is logged giving their count, total, mean, min and max elapsed times. Create one with
+/// `aggregate_scope()` or the `aggregate_scope!` macro. Scopes can be nested, and a timer
+/// contributes to every live scope for its name.
+pub struct AggregateScope {
+    id: usize,
+    // Thread-local state, so the guard must not be sent to another thread.
+    _not_send: std::marker::PhantomData<*const ()>,
+}
+
+/// Aggregates the timers named `name` on the current thread until the returned guard is
+/// dropped, then logs a summary of them. Nothing is logged if no such timer finished.
+/// Timers are created regardless of their log level while any scope is alive, so that
+/// they can be counted.
+pub fn aggregate_scope(name: impl Into<String>) -> AggregateScope {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    SCOPES.with(|s| s.borrow_mut().push(Scope { id, name: name.into(), stats: None }));
+    ACTIVE.fetch_add(1, Ordering::Relaxed);
+    AggregateScope { id, _not_send: std::marker::PhantomData }
+}
+
+impl Drop for AggregateScope {
+    fn drop(&mut self) {
+        let scope = SCOPES.with(|s| {
+            let mut scopes = s.borrow_mut();
+            let index = scopes.iter().rposition(|scope| scope.id == self.id);
+            index.map(|i| scopes.remove(i))
+        });
+        ACTIVE.fetch_sub(1, Ordering::Relaxed);
+
+        if let Some(Scope { name, stats: Some(stats), .. }) = scope {
+            stats::emit_stats(&name, &stats);
+        }
+    }
+}
+
+/// Returns true if any aggregation scope is alive, on any thread.
+#[inline]
+pub(crate) fn any_active() -> bool {
+    ACTIVE.load(Ordering::Relaxed) > 0
+}
+
+/// Adds a finished call of the named timer to the matching scopes on this thread.
+pub(crate) fn record(name: &TimerName, elapsed: Duration) {
+    if !any_active() {
+        return;
+    }
+
+    SCOPES.with(|s| {
+        let mut scopes = s.borrow_mut();
+        let mut resolved = None;
+        for scope in scopes.iter_mut() {
+            let matches = match name {
+                TimerName::Str(name) => *name == scope.name,
+                TimerName::Owned(name) => *name == scope.name,
+                TimerName::Id(_) => *resolved.get_or_insert_with(|| name.to_string()) == scope.name,
+            };
+            if matches {
+                match scope.stats.as_mut() {
+                    Some(stats) => stats.add(elapsed),
+                    None => scope.stats = Some(TimerStats::new(elapsed)),
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,128 @@
+use crate::TimerName;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::Duration;
+
+/// The number of recent calls of each timer name that are kept for comparison.
+const HISTORY_LEN: usize = 256;
+
+/// The number of calls of a timer name that must be recorded before any is reported as
+/// an anomaly. With fewer, a high percentile is simply the slowest call so far, and any
+/// new slowest call would be reported.
+const MIN_HISTORY: usize = 100;
+
+// The percentile, as the bits of an f64, 0 meaning that detection is off.
+static PERCENTILE: AtomicU64 = AtomicU64::new(0);
+
+// Like the statistics, timers named by strings and by ids are kept separately, so that
+// recording a call never needs to allocate once the name has been seen.
+#[derive(Default)]
+struct History {
+    by_name: HashMap<String, Calls>,
+    by_id: HashMap<u32, Calls>,
+}
+
+// The recent calls of one timer name, both in the order they were made, so that the
+// oldest can be dropped, and sorted, so that the percentile is a lookup.
+#[derive(Default)]
+struct Calls {
+    recent: VecDeque<Duration>,
+    sorted: Vec<Duration>,
+}
+
+impl Calls {
+    fn with_capacity(capacity: usize) -> Self {
+        Calls { recent: VecDeque::with_capacity(capacity), sorted: Vec::with_capacity(capacity) }
+    }
+
+    fn len(&self) -> usize {
+        self.recent.len()
+    }
+
+    fn push(&mut self, elapsed: Duration) {
+        if self.recent.len() == HISTORY_LEN {
+            if let Some(oldest) = self.recent.pop_front() {
+                if let Ok(index) = self.sorted.binary_search(&oldest) {
+                    self.sorted.remove(index);
+                }
+            }
+        }
+        self.recent.push_back(elapsed);
+        let index = self.sorted.partition_point(|&call| call <= elapsed);
+        self.sorted.insert(index, elapsed);
+    }
+
+    // The nearest-rank percentile of the calls, which must not be empty.
+    fn percentile(&self, percentile: f64) -> Duration {
+        let rank = (percentile * self.sorted.len() as f64).ceil() as usize;
+        self.sorted[rank.clamp(1, self.sorted.len()) - 1]
+    }
+}
+
+fn history() -> MutexGuard<'static, History> {
+    static HISTORY: OnceLock<Mutex<History>> = OnceLock::new();
+    HISTORY.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Turns on the detection of calls which are unusually slow compared with the recent
+/// calls of the same timer. Each timer name keeps the elapsed times of its last 256 calls,
+/// and a call which is slower than `percentile` of them, for example `Some(0.99)` for the
+/// 99th percentile, has its 'finished' message logged at `Warn` level, or its own level
+/// if that is more severe, with `Anomaly=true` added. The message is logged even if the
+/// call is below the threshold. No call is reported until its name has 100 calls of
+/// history, so that the baseline means something.
+///
+/// This adapts to each timer's own baseline, so one setting suits fast and slow timers
+/// alike, unlike a fixed threshold or level breakpoints. Timers are created regardless of
+/// their log level while this is on, so that their history is complete. `None`, the
+/// default, turns detection off and discards the history. A NaN percentile is ignored,
+/// and causes a panic in debug builds.
+///
+/// ```norun
+/// logging_timer::set_anomaly_detection(Some(0.99));
+/// ```
+pub fn set_anomaly_detection(percentile: Option<f64>) {
+    if percentile.is_some_and(f64::is_nan) {
+        debug_assert!(false, "the anomaly percentile is NaN");
+        return;
+    }
+
+    let percentile = percentile.map_or(0.0, |p| p.clamp(f64::MIN_POSITIVE, 1.0));
+    PERCENTILE.store(percentile.to_bits(), Ordering::Relaxed);
+    if percentile == 0.0 {
+        *history() = History::default();
+    }
+}
+
+#[inline]
+pub(crate) fn is_enabled() -> bool {
+    PERCENTILE.load(Ordering::Relaxed) != 0
+}
+
+/// Records a finished call of the named timer, returning true if it was slower than the
+/// percentile of the calls before it.
+pub(crate) fn check(name: &TimerName, elapsed: Duration) -> bool {
+    if !is_enabled() {
+        return false;
+    }
+
+    let percentile = f64::from_bits(PERCENTILE.load(Ordering::Relaxed));
+    let mut history = history();
+    let calls = match name {
+        TimerName::Id(id) => history.by_id.entry(*id).or_default(),
+        TimerName::Str(name) => calls_by_name(&mut history, name),
+        TimerName::Owned(name) => calls_by_name(&mut history, name),
+    };
+
+    let anomalous = calls.len() >= MIN_HISTORY && elapsed > calls.percentile(percentile);
+    calls.push(elapsed);
+    anomalous
+}
+
+fn calls_by_name<'a>(history: &'a mut History, name: &str) -> &'a mut Calls {
+    if !history.by_name.contains_key(name) {
+        history.by_name.insert(name.to_string(), Calls::with_capacity(HISTORY_LEN));
+    }
+    history.by_name.get_mut(name).unwrap()
+}
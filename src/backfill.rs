@@ -0,0 +1,65 @@
+use crate::{TimerName, TimerTarget, format};
+use std::fmt;
+use std::panic::Location;
+use std::time::Instant;
+
+/// Logs a 'finished' message for an operation that was timed by some other means, from
+/// its recorded `start` and `end`, without running a live timer. The message is the same
+/// as a timer called `name` would have logged, so this can be used to replay or backfill
+/// timings measured elsewhere into the same log. `args` adds further information to the
+/// message, as with `finish!`. If `end` is before `start` the elapsed time is zero.
+///
+/// The source location logged is that of the call.
+///
+/// ```norun
+/// for sample in recorded_samples {
+///     let (start, end) = (sample.start, sample.end);
+///     log_timer_span("RENDER", start, end, Level::Info, Some(format_args!("Frame={}", sample.frame)));
+/// }
+/// ```
+#[track_caller]
+pub fn log_timer_span<'name>(
+    name: impl Into<TimerName<'name>>,
+    start: Instant,
+    end: Instant,
+    level: ::log::Level,
+    args: Option<fmt::Arguments>,
+) {
+    if !(::log::log_enabled!(level) || crate::stderr::fallback_active()) {
+        return;
+    }
+
+    let location = Location::caller();
+    let operation_id = crate::operation::current();
+    let thread_label = crate::thread_label::current();
+    let message = format::Message {
+        target: TimerTarget::Finished,
+        file: location.file(),
+        line: location.line(),
+        name: &name.into(),
+        operation_id: operation_id.as_deref(),
+        thread_label: thread_label.as_deref(),
+        tracing_span: None,
+        span: None,
+        elapsed: Some(end.saturating_duration_since(start)),
+        request_elapsed: None,
+        duration_format: None,
+        parent_fraction: None,
+        wall_clock: None,
+        cpu: None,
+        queue: None,
+        phases: None,
+        extra_info: None,
+        args,
+    };
+
+    crate::emit(
+        &::log::RecordBuilder::new()
+            .level(level)
+            .target(format::target(TimerTarget::Finished))
+            .file(Some(location.file()))
+            .line(Some(location.line()))
+            .args(format_args!("{}", message))
+            .build(),
+    );
+}
@@ -0,0 +1,95 @@
+use crate::TimerRecord;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::SyncSender;
+use std::sync::{Mutex, MutexGuard};
+
+/// What to do with a timer record when the timer channel is full.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum WhenFull {
+    /// Discard the record, so that timers never hold up the code being timed.
+    /// This is the default.
+    #[default]
+    Drop,
+    /// Wait until the consumer makes room in the channel.
+    Block,
+}
+
+struct Channel {
+    sender: SyncSender<TimerRecord>,
+    when_full: WhenFull,
+}
+
+static CHANNEL: Mutex<Option<Channel>> = Mutex::new(None);
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static CHANNEL_ONLY: AtomicBool = AtomicBool::new(false);
+
+fn channel() -> MutexGuard<'static, Option<Channel>> {
+    CHANNEL.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Sends a `TimerRecord` for every message produced by every timer on `sender`, for
+/// consumers that want to build their own dashboards or aggregations. Records are sent
+/// as well as being logged, unless `set_timer_channel_only(true)` is called. Records are
+/// sent regardless of the log level, which is included in the record for filtering.
+///
+/// Use `std::sync::mpsc::sync_channel` to create the channel; `when_full` controls what
+/// happens when it is full. If the receiver is dropped, the channel is removed.
+///
+/// # Examples
+///
+/// ```norun
+/// let (sender, receiver) = std::sync::mpsc::sync_channel(1000);
+/// logging_timer::set_timer_channel(sender, WhenFull::Drop);
+///
+/// std::thread::spawn(move || {
+///     for record in receiver {
+///         dashboard.update(&record.name, record.elapsed);
+///     }
+/// });
+/// ```
+pub fn set_timer_channel(sender: SyncSender<TimerRecord>, when_full: WhenFull) {
+    *channel() = Some(Channel { sender, when_full });
+    ACTIVE.store(true, Ordering::Relaxed);
+}
+
+/// Removes the channel installed by `set_timer_channel`.
+pub fn clear_timer_channel() {
+    ACTIVE.store(false, Ordering::Relaxed);
+    *channel() = None;
+}
+
+/// When set to true, timer messages are only sent to the timer channel and are not
+/// logged, as long as a channel is installed. The default is false.
+pub fn set_timer_channel_only(enabled: bool) {
+    CHANNEL_ONLY.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns true if a channel is installed.
+#[inline]
+pub(crate) fn is_active() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+/// Returns true if messages should go only to the channel and not to the log.
+pub(crate) fn is_exclusive() -> bool {
+    is_active() && CHANNEL_ONLY.load(Ordering::Relaxed)
+}
+
+pub(crate) fn send(record: TimerRecord) {
+    // Take a copy of the sender so that a blocking send does not hold the lock.
+    let (sender, when_full) = match channel().as_ref() {
+        Some(channel) => (channel.sender.clone(), channel.when_full),
+        None => return,
+    };
+
+    let disconnected = match when_full {
+        WhenFull::Drop => {
+            matches!(sender.try_send(record), Err(std::sync::mpsc::TrySendError::Disconnected(_)))
+        }
+        WhenFull::Block => sender.send(record).is_err(),
+    };
+
+    if disconnected {
+        clear_timer_channel();
+    }
+}
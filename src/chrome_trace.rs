@@ -0,0 +1,157 @@
+use std::cell::Cell;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+struct Trace {
+    out: Box<dyn Write + Send>,
+    start: Instant,
+    first_event: bool,
+}
+
+static TRACE: Mutex<Option<Trace>> = Mutex::new(None);
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static NEXT_TID: AtomicU32 = AtomicU32::new(1);
+
+thread_local! {
+    // A small number identifying this thread in the trace; 0 means not yet assigned.
+    static TID: Cell<u32> = const { Cell::new(0) };
+}
+
+fn trace() -> MutexGuard<'static, Option<Trace>> {
+    TRACE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Starts writing every timer to `out` in the Chrome Trace Event format, as a 'complete'
+/// event when the timer finishes, so that nested timers appear as a flame chart on a
+/// timeline when the file is loaded into Perfetto (<https://ui.perfetto.dev>) or
+/// `chrome://tracing`. Each thread gets its own track, that of the thread which started
+/// the timer. Since each event carries its own start and duration, timers which do not
+/// finish in the reverse order that they started, such as those finished in another
+/// scope or on another thread, are still shown with the right times.
+/// All timers are traced regardless of their log level, and continue to be logged as
+/// normal. Call `finish_chrome_trace` to complete the file.
+///
+/// # Examples
+///
+/// ```norun
+/// let file = std::io::BufWriter::new(std::fs::File::create("trace.json")?);
+/// logging_timer::start_chrome_trace(file)?;
+/// run_workload();
+/// logging_timer::finish_chrome_trace()?;
+/// ```
+pub fn start_chrome_trace(out: impl Write + Send + 'static) -> io::Result<()> {
+    let mut out: Box<dyn Write + Send> = Box::new(out);
+    out.write_all(b"[\n")?;
+    *trace() = Some(Trace { out, start: crate::clock::now(), first_event: true });
+    ACTIVE.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Completes the trace started by `start_chrome_trace`, writing the end of the JSON array
+/// and flushing the output. Timers that have not finished yet do not appear in the trace.
+pub fn finish_chrome_trace() -> io::Result<()> {
+    ACTIVE.store(false, Ordering::Relaxed);
+    match trace().take() {
+        Some(mut trace) => {
+            trace.out.write_all(b"\n]\n")?;
+            trace.out.flush()
+        }
+        None => Ok(()),
+    }
+}
+
+/// Returns true if a trace is being written.
+#[inline]
+pub(crate) fn is_active() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+/// Returns the trace id of the current thread, writing its name into the trace the
+/// first time the thread is seen.
+pub(crate) fn current_tid() -> u32 {
+    TID.with(|tid| {
+        if tid.get() == 0 {
+            tid.set(NEXT_TID.fetch_add(1, Ordering::Relaxed));
+            let thread = std::thread::current();
+            let name =
+                thread.name().map(str::to_string).unwrap_or_else(|| format!("{:?}", thread.id()));
+            let mut event = String::new();
+            let _ = write!(
+                event,
+                r#"{{"name":"thread_name","ph":"M","pid":{},"tid":{},"args":{{"name":"#,
+                std::process::id(),
+                tid.get()
+            );
+            push_json_string(&mut event, &name);
+            event.push_str("}}");
+            write_event(&event);
+        }
+        tid.get()
+    })
+}
+
+/// Writes the 'complete' event of a timer which started at `start` and ran for `elapsed`.
+pub(crate) fn complete(
+    name: &dyn std::fmt::Display,
+    extra_info: Option<&str>,
+    tid: u32,
+    start: Instant,
+    elapsed: Duration,
+) {
+    let mut event = String::new();
+    event.push_str(r#"{"name":"#);
+    push_json_string(&mut event, &name.to_string());
+    let _ = write!(
+        event,
+        r#","ph":"X","pid":{},"tid":{},"ts":{},"dur":{:.3}"#,
+        std::process::id(),
+        tid,
+        ts(start),
+        elapsed.as_nanos() as f64 / 1000.0
+    );
+    if let Some(info) = extra_info {
+        event.push_str(r#","args":{"info":"#);
+        push_json_string(&mut event, info);
+        event.push('}');
+    }
+    event.push('}');
+    write_event(&event);
+}
+
+// The timestamp of `start`, in microseconds since the start of the trace.
+fn ts(start: Instant) -> String {
+    let trace_start = trace().as_ref().map(|t| t.start).unwrap_or(start);
+    let micros = start.saturating_duration_since(trace_start).as_nanos() as f64 / 1000.0;
+    format!("{:.3}", micros)
+}
+
+fn write_event(event: &str) {
+    if let Some(trace) = trace().as_mut() {
+        let separator: &[u8] = if trace.first_event { b"" } else { b",\n" };
+        trace.first_event = false;
+        // There is nowhere sensible to report a failure to write, and timers must not
+        // fail the code they are timing, so errors are ignored.
+        let _ = trace.out.write_all(separator).and_then(|_| trace.out.write_all(event.as_bytes()));
+    }
+}
+
+fn push_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
@@ -0,0 +1,157 @@
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+/// A source of the current time for timers. The default is the system's monotonic
+/// clock, i.e. `Instant::now()`. A different clock can be installed using `set_clock`,
+/// for example to make timers measure simulated time in a discrete-event simulation.
+///
+/// Whether the default clock counts time during which the machine was suspended depends
+/// on the platform: it does on Windows, but not on Linux or macOS. For long-lived timers
+/// on laptops and VMs this can be chosen explicitly by installing `MonotonicClock`, which
+/// never counts it, or `BoottimeClock`, which does where the platform supports it.
+pub trait Clock: Send + Sync {
+    /// Returns the current time.
+    fn now(&self) -> Instant;
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now(&self) -> Instant {
+        (**self).now()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Box<C> {
+    fn now(&self) -> Instant {
+        (**self).now()
+    }
+}
+
+/// The system's monotonic clock, `Instant::now()`. This is the default clock. On Linux and
+/// macOS it stops while the machine is suspended, so a timer that spans a suspend does not
+/// include the time spent asleep. On Windows it keeps counting.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct MonotonicClock;
+
+impl Clock for MonotonicClock {
+    #[inline]
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock which keeps counting while the machine is suspended, so that a timer which
+/// spans a suspend and resume includes the time spent asleep. On Linux this reads
+/// `clock_gettime(CLOCK_BOOTTIME)`, falling back to `Instant::now()` if the kernel does
+/// not support it. Elsewhere it is the same as `MonotonicClock`, which already counts
+/// suspended time on Windows but not on macOS.
+///
+/// ```norun
+/// logging_timer::set_clock(logging_timer::BoottimeClock::new()).unwrap();
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct BoottimeClock {
+    // The boottime reading corresponding to `epoch`. Instants are produced by adding
+    // the boottime that has passed since then to `epoch`. `None` if the clock could not
+    // be read, in which case `Instant::now()` is used instead.
+    #[cfg(target_os = "linux")]
+    boot_epoch: Option<Duration>,
+    #[cfg(target_os = "linux")]
+    epoch: Instant,
+}
+
+impl BoottimeClock {
+    /// Creates a boottime clock.
+    pub fn new() -> Self {
+        BoottimeClock {
+            #[cfg(target_os = "linux")]
+            boot_epoch: sys::clock_gettime(sys::CLOCK_BOOTTIME),
+            #[cfg(target_os = "linux")]
+            epoch: Instant::now(),
+        }
+    }
+}
+
+impl Default for BoottimeClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for BoottimeClock {
+    #[inline]
+    fn now(&self) -> Instant {
+        #[cfg(target_os = "linux")]
+        if let (Some(boot_epoch), Some(boottime)) =
+            (self.boot_epoch, sys::clock_gettime(sys::CLOCK_BOOTTIME))
+        {
+            return self.epoch + boottime.saturating_sub(boot_epoch);
+        }
+        Instant::now()
+    }
+}
+
+/// Returns the CPU time used so far by the current thread, or `None` if it cannot be
+/// measured on this platform.
+pub(crate) fn thread_cpu_time() -> Option<Duration> {
+    #[cfg(target_os = "linux")]
+    return sys::clock_gettime(sys::CLOCK_THREAD_CPUTIME_ID);
+    #[cfg(not(target_os = "linux"))]
+    return None;
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::time::Duration;
+
+    pub(super) use libc::{CLOCK_BOOTTIME, CLOCK_THREAD_CPUTIME_ID};
+
+    /// Reads one of the clocks above, or returns `None` if the kernel does not support it.
+    /// CLOCK_BOOTTIME is the time since boot, including any time spent suspended, and
+    /// CLOCK_THREAD_CPUTIME_ID the CPU time of this thread.
+    pub(super) fn clock_gettime(clock_id: libc::clockid_t) -> Option<Duration> {
+        let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+        // SAFETY: `ts` is a valid, writable timespec.
+        match unsafe { libc::clock_gettime(clock_id, &mut ts) } {
+            0 => Some(Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)),
+            _ => None,
+        }
+    }
+}
+
+static CLOCK: OnceLock<Box<dyn Clock>> = OnceLock::new();
+
+/// Installs the clock used by all timers. Like installing a logger, this can only be
+/// done once, and should be done before any timers are created; otherwise a timer
+/// could be started by one clock and finished by another. If you need to keep a handle
+/// to the clock, for example to advance simulated time, install an `Arc` of it.
+pub fn set_clock(clock: impl Clock + 'static) -> Result<(), SetClockError> {
+    CLOCK.set(Box::new(clock)).map_err(|_| SetClockError(()))
+}
+
+/// The error returned by `set_clock` if a clock has already been installed.
+#[derive(Debug)]
+pub struct SetClockError(());
+
+impl fmt::Display for SetClockError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a clock has already been installed for logging_timer")
+    }
+}
+
+impl std::error::Error for SetClockError {}
+
+/// Returns the current time according to the installed clock.
+#[inline]
+pub(crate) fn now() -> Instant {
+    match CLOCK.get() {
+        Some(clock) => clock.now(),
+        None => Instant::now(),
+    }
+}
+
+/// Returns the time that has passed since `start` according to the installed clock.
+#[inline]
+pub(crate) fn elapsed_since(start: Instant) -> Duration {
+    now().saturating_duration_since(start)
+}
@@ -0,0 +1,32 @@
+use crate::LoggingTimer;
+use crate::format::{FormattedDuration, separator};
+use std::time::Duration;
+
+/// Finishes the timer created by `ab_time!`, logging the time taken by each of the
+/// two implementations and which was faster. `elapsed_a` is the time at which the
+/// first implementation finished; the second one ran from then until now.
+#[doc(hidden)]
+pub fn __ab_finish(tmr: LoggingTimer, elapsed_a: Duration) {
+    let format = tmr.duration_format();
+    let elapsed_b = tmr.elapsed().saturating_sub(elapsed_a);
+    let (faster, fast, slow) = if elapsed_a <= elapsed_b {
+        ("A", elapsed_a, elapsed_b)
+    } else {
+        ("B", elapsed_b, elapsed_a)
+    };
+    let sep = separator();
+    let ratio = slow.as_secs_f64() / fast.as_secs_f64().max(f64::MIN_POSITIVE);
+
+    tmr.finish(Some(format_args!(
+        "A={}{}B={}{}Faster={}{}Difference={}{}Ratio={:.2}",
+        FormattedDuration(elapsed_a, format),
+        sep,
+        FormattedDuration(elapsed_b, format),
+        sep,
+        faster,
+        sep,
+        FormattedDuration(slow - fast, format),
+        sep,
+        ratio
+    )));
+}
@@ -0,0 +1,189 @@
+use crate::format::FormattedDuration;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// The deadline of a timer, see `LoggingTimer::with_deadline`.
+pub(crate) struct Deadline {
+    pub(crate) budget: Duration,
+    // Set by whichever of the timer and the watcher reports on the deadline first,
+    // so that it is only ever reported once.
+    reported: Arc<AtomicBool>,
+    // The key of the deadline in the watcher's queue, if it is being watched.
+    #[cfg(feature = "deadline")]
+    watch_key: Option<watcher::Key>,
+}
+
+impl Deadline {
+    pub(crate) fn new(budget: Duration) -> Self {
+        Deadline {
+            budget,
+            reported: Arc::new(AtomicBool::new(false)),
+            #[cfg(feature = "deadline")]
+            watch_key: None,
+        }
+    }
+
+    /// Called when the timer finishes. Logs a message if the deadline has been
+    /// exceeded and nothing has reported it yet, and stops watching it.
+    pub(crate) fn finish(&self, location: Location, name: &dyn fmt::Display, elapsed: Duration) {
+        if !self.reported.swap(true, Ordering::SeqCst) {
+            #[cfg(feature = "deadline")]
+            if let Some(key) = self.watch_key {
+                watcher::unwatch(key);
+            }
+            if elapsed > self.budget {
+                report(location, name, self.budget, elapsed);
+            }
+        }
+    }
+}
+
+/// Where a timer was created.
+#[derive(Copy, Clone)]
+pub(crate) struct Location {
+    pub(crate) file: &'static str,
+    pub(crate) module_path: Option<&'static str>,
+    pub(crate) line: u32,
+}
+
+/// Logs the fact that a timer has exceeded its deadline, as a warning.
+fn report(location: Location, name: &dyn fmt::Display, budget: Duration, elapsed: Duration) {
+    let level = ::log::Level::Warn;
+    if !(::log::log_enabled!(level) || crate::stderr::fallback_active()) {
+        return;
+    }
+
+    let sep = crate::format::separator();
+    crate::emit(
+        &::log::RecordBuilder::new()
+            .level(level)
+            .target(crate::format::target(crate::TimerTarget::Executing))
+            .file(Some(location.file))
+            .module_path(location.module_path)
+            .line(Some(location.line))
+            .args(format_args!(
+                "{}{}Elapsed={}{}DeadlineExceeded={}",
+                name,
+                sep,
+                FormattedDuration(elapsed, None),
+                sep,
+                FormattedDuration(budget, None)
+            ))
+            .build(),
+    );
+}
+
+/// With the `deadline` feature a single background thread watches the deadlines of
+/// all live timers, so that a breach is reported the moment it happens rather than
+/// when the timer finishes.
+#[cfg(feature = "deadline")]
+mod watcher {
+    use super::{Deadline, Location, report};
+    use std::collections::BTreeMap;
+    use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+    use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock};
+    use std::time::{Duration, Instant};
+
+    /// Identifies a watched deadline: when it falls due, and a sequence number to tell
+    /// apart deadlines which fall due at the same instant.
+    pub(crate) type Key = (Instant, u64);
+
+    static NEXT_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+    struct Entry {
+        budget: Duration,
+        reported: Arc<AtomicBool>,
+        name: String,
+        location: Location,
+    }
+
+    // The deadlines in the order they fall due. They are removed when their timer
+    // finishes, so only the deadlines of live timers are kept.
+    #[derive(Default)]
+    struct Watcher {
+        queue: Mutex<BTreeMap<Key, Entry>>,
+        wake: Condvar,
+    }
+
+    impl Watcher {
+        fn queue(&self) -> MutexGuard<'_, BTreeMap<Key, Entry>> {
+            self.queue.lock().unwrap_or_else(|e| e.into_inner())
+        }
+    }
+
+    fn watcher() -> &'static Watcher {
+        static WATCHER: OnceLock<Watcher> = OnceLock::new();
+        static STARTED: OnceLock<()> = OnceLock::new();
+
+        let watcher = WATCHER.get_or_init(Watcher::default);
+        STARTED.get_or_init(|| {
+            std::thread::Builder::new()
+                .name("logging_timer_deadlines".to_string())
+                .spawn(move || watcher.run())
+                .expect("failed to spawn the logging_timer deadline thread");
+        });
+        watcher
+    }
+
+    impl Watcher {
+        fn run(&self) {
+            let mut queue = self.queue();
+            loop {
+                let now = Instant::now();
+                match queue.keys().next() {
+                    None => {
+                        queue = self.wake.wait(queue).unwrap_or_else(|e| e.into_inner());
+                    }
+                    Some(&(at, _)) if at > now => {
+                        queue = self
+                            .wake
+                            .wait_timeout(queue, at - now)
+                            .unwrap_or_else(|e| e.into_inner())
+                            .0;
+                    }
+                    Some(&key) => {
+                        let entry = queue.remove(&key).expect("first entry");
+                        if !entry.reported.swap(true, Ordering::SeqCst) {
+                            drop(queue);
+                            let elapsed = entry.budget + (Instant::now() - key.0);
+                            report(entry.location, &entry.name, entry.budget, elapsed);
+                            queue = self.queue();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Starts watching the deadline of a timer which has been running for `elapsed`.
+    pub(crate) fn watch(
+        deadline: &mut Deadline,
+        location: Location,
+        name: String,
+        elapsed: Duration,
+    ) {
+        let at = Instant::now() + deadline.budget.saturating_sub(elapsed);
+        let key = (at, NEXT_SEQUENCE.fetch_add(1, Ordering::Relaxed));
+        let entry = Entry {
+            budget: deadline.budget,
+            reported: Arc::clone(&deadline.reported),
+            name,
+            location,
+        };
+        deadline.watch_key = Some(key);
+
+        let watcher = watcher();
+        watcher.queue().insert(key, entry);
+        watcher.wake.notify_one();
+    }
+
+    /// Stops watching a deadline, because its timer has finished.
+    pub(crate) fn unwatch(key: Key) {
+        watcher().queue().remove(&key);
+    }
+}
+
+#[cfg(feature = "deadline")]
+pub(crate) use watcher::watch;
@@ -0,0 +1,24 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turns deterministic mode on or off. This is meant for tests which make assertions
+/// about timer output. In deterministic mode every message is produced synchronously,
+/// on the thread that caused it, in the order that things happen, so a timer's starting
+/// message always precedes its finished message and nothing appears in between from
+/// elsewhere. Specifically:
+///
+/// * Deadlines are not watched by the background thread of the `deadline` feature;
+///   a deadline that has been exceeded is reported when the timer finishes instead.
+/// * The timer statistics are not dumped automatically, since when that happens
+///   depends on the time; call `dump_timer_stats` at the point you want them.
+///
+/// The default is off.
+pub fn set_deterministic_mode(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+#[inline]
+pub(crate) fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
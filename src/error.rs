@@ -0,0 +1,119 @@
+use crate::LoggingTimer;
+use crate::format::FormattedDuration;
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+/// An error to which the name and elapsed time of a timer have been attached, created
+/// by `TimerResultExt::with_timer`. It displays as the original error followed by the
+/// timing, for example `connection reset (FETCH, Elapsed=30.002s)`, and since it
+/// implements `std::error::Error` it can be converted into an `anyhow::Error` or
+/// wrapped by a `thiserror` error like any other.
+#[derive(Debug)]
+pub struct TimedError<E> {
+    error: E,
+    timing: Option<(String, Duration)>,
+}
+
+impl<E> TimedError<E> {
+    /// Returns the original error.
+    pub fn error(&self) -> &E {
+        &self.error
+    }
+
+    /// Returns the original error, discarding the timing.
+    pub fn into_inner(self) -> E {
+        self.error
+    }
+
+    /// Returns the name of the timer, or `None` if the timer was disabled.
+    pub fn timer_name(&self) -> Option<&str> {
+        self.timing.as_ref().map(|(name, _)| name.as_str())
+    }
+
+    /// Returns the elapsed time of the timer when the error was returned,
+    /// or `None` if the timer was disabled.
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.timing.as_ref().map(|(_, elapsed)| *elapsed)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for TimedError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.timing {
+            Some((name, elapsed)) => {
+                write!(
+                    f,
+                    "{} ({}, Elapsed={})",
+                    self.error,
+                    name,
+                    FormattedDuration(*elapsed, None)
+                )
+            }
+            None => write!(f, "{}", self.error),
+        }
+    }
+}
+
+impl<E: Error> Error for TimedError<E> {
+    /// The original error is included in the message, so its source is returned
+    /// here rather than the error itself, to avoid it appearing twice in reports.
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.error.source()
+    }
+}
+
+/// Extends `Result` with a way of attaching timing information to errors.
+pub trait TimerResultExt<T, E> {
+    /// If the result is an error, wraps it in a `TimedError` which records the name and
+    /// elapsed time of the timer, so that reports show how long the operation ran before
+    /// it failed. `tmr` may be a `&LoggingTimer` or the `&Option<LoggingTimer>` returned
+    /// by the timer macros; if the timer is disabled the error is wrapped without timing.
+    ///
+    /// ```norun
+    /// let tmr = timer!("FETCH");
+    /// let body = client.get(url).with_timer(&tmr)?;
+    /// ```
+    fn with_timer<'a, 'name: 'a>(
+        self,
+        tmr: impl Into<Option<&'a LoggingTimer<'name>>>,
+    ) -> Result<T, TimedError<E>>;
+}
+
+impl<T, E> TimerResultExt<T, E> for Result<T, E> {
+    fn with_timer<'a, 'name: 'a>(
+        self,
+        tmr: impl Into<Option<&'a LoggingTimer<'name>>>,
+    ) -> Result<T, TimedError<E>> {
+        self.map_err(|error| TimedError {
+            error,
+            timing: tmr.into().map(|tmr| (tmr.name().to_string(), tmr.elapsed())),
+        })
+    }
+}
+
+/// Implementation of `#[time(result)]`. If the function returned an error, finishes the
+/// timer with the error's `Debug` representation, at the `on_err` level if one was given.
+/// A successful result is left for the timer to log normally when it is dropped.
+#[doc(hidden)]
+pub fn __finish_result<T, E: fmt::Debug>(
+    tmr: &Option<LoggingTimer>,
+    result: &Result<T, E>,
+    on_err: Option<::log::Level>,
+) {
+    if let (Some(tmr), Err(error)) = (tmr, result) {
+        match on_err {
+            Some(level) => tmr.finish_at(level, Some(format_args!("Error={:?}", error))),
+            None => tmr.finish(Some(format_args!("Error={:?}", error))),
+        }
+    }
+}
+
+/// Implementation of `#[time(result_len)]`. Finishes the timer with the length of the
+/// value the function returned.
+#[doc(hidden)]
+pub fn __finish_len(tmr: &Option<LoggingTimer>, len: usize) {
+    if let Some(tmr) = tmr {
+        tmr.finish(Some(format_args!("Len={}", len)));
+    }
+}
@@ -0,0 +1,90 @@
+use crate::TimerName;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// The details of a timer that is about to log its 'finished' message, passed to the
+/// hook installed by `set_pre_finish_hook`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct FinishContext<'a> {
+    /// The name of the timer.
+    pub name: &'a TimerName<'a>,
+    /// The level the message will be logged at.
+    pub level: ::log::Level,
+    /// The elapsed time of the timer.
+    pub elapsed: Duration,
+    /// The extra information given when the timer was created.
+    pub extra_info: Option<&'a str>,
+    /// The file in which the timer was created.
+    pub file: &'static str,
+    /// The module in which the timer was created, if it is known.
+    pub module_path: Option<&'static str>,
+    /// The line on which the timer was created.
+    pub line: u32,
+}
+
+/// What should happen to a 'finished' message after the pre-finish hook has seen it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FinishDecision {
+    /// Log the message as normal.
+    Emit,
+    /// Do not log the message.
+    Suppress,
+    /// Log the message with this text added to the end of it.
+    EmitWith(String),
+}
+
+type Hook = Arc<dyn Fn(&FinishContext) -> FinishDecision + Send + Sync>;
+
+static HOOK: RwLock<Option<Hook>> = RwLock::new(None);
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Installs a function which is called just before every timer logs its 'finished'
+/// message, and decides whether the message is logged, suppressed, or logged with some
+/// extra text. This allows filtering and enrichment that the settings cannot express,
+/// for example suppressing the messages of a noisy timer except for one customer, or
+/// adding the current size of a cache. It is not called for messages which are already
+/// suppressed, for example by a threshold. Installing a hook replaces any previous one.
+///
+/// The function may be called from several threads at once, so it must be `Fn`. It is
+/// called without any lock held, so it can safely use timers itself.
+///
+/// # Examples
+///
+/// ```norun
+/// logging_timer::set_pre_finish_hook(|ctx| {
+///     if ctx.elapsed < Duration::from_millis(5) && ctx.name.to_string() == "POLL" {
+///         FinishDecision::Suppress
+///     } else {
+///         FinishDecision::EmitWith(format!("CacheSize={}", cache_size()))
+///     }
+/// });
+/// ```
+pub fn set_pre_finish_hook<F>(hook: F)
+where
+    F: Fn(&FinishContext) -> FinishDecision + Send + Sync + 'static,
+{
+    *HOOK.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(hook));
+    ACTIVE.store(true, Ordering::Relaxed);
+}
+
+/// Removes the hook installed by `set_pre_finish_hook`.
+pub fn clear_pre_finish_hook() {
+    ACTIVE.store(false, Ordering::Relaxed);
+    *HOOK.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Passes the context to the hook, if there is one, and returns what to do with the
+/// 'finished' message.
+pub(crate) fn decide(context: &FinishContext) -> FinishDecision {
+    if !ACTIVE.load(Ordering::Relaxed) {
+        return FinishDecision::Emit;
+    }
+
+    let hook = HOOK.read().unwrap_or_else(|e| e.into_inner()).clone();
+    match hook {
+        Some(hook) => hook(context),
+        None => FinishDecision::Emit,
+    }
+}
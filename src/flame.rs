@@ -0,0 +1,86 @@
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+static OUT: Mutex<Option<Box<dyn Write + Send>>> = Mutex::new(None);
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+fn out() -> MutexGuard<'static, Option<Box<dyn Write + Send>>> {
+    OUT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Starts writing every timer to `out` in the folded stack format used by `tracing-flame`,
+/// so that flame graphs can be drawn from existing timers with `inferno-flamegraph` or
+/// `flamegraph.pl`. When a timer finishes a line is written with the name of its thread,
+/// the names of the timers it is nested inside and its own name, separated by semicolons,
+/// followed by the time in nanoseconds it spent outside of any nested timer:
+///
+/// ```text
+/// main;load_config;parse 1534000
+/// ```
+///
+/// Nesting is tracked per thread (this turns on `enable_timer_nesting`'s tracking), so a
+/// timer which is finished on a different thread from the one that started it is not
+/// written. All timers are written regardless of their log level, and continue to be
+/// logged as normal. Call `finish_flame_graph` to flush the output.
+///
+/// # Examples
+///
+/// ```norun
+/// let file = std::io::BufWriter::new(std::fs::File::create("timers.folded")?);
+/// logging_timer::start_flame_graph(file);
+/// run_workload();
+/// logging_timer::finish_flame_graph()?;
+/// // Then: inferno-flamegraph < timers.folded > flamegraph.svg
+/// ```
+pub fn start_flame_graph(out: impl Write + Send + 'static) {
+    *self::out() = Some(Box::new(out));
+    ACTIVE.store(true, Ordering::Relaxed);
+}
+
+/// Stops writing timers in the folded stack format and flushes the output. Timers that
+/// have not finished yet are not written.
+pub fn finish_flame_graph() -> io::Result<()> {
+    ACTIVE.store(false, Ordering::Relaxed);
+    match out().take() {
+        Some(mut out) => out.flush(),
+        None => Ok(()),
+    }
+}
+
+/// Returns true if folded stacks are being written.
+#[inline]
+pub(crate) fn is_active() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+/// Writes the line for a timer called `name`, nested inside the timers called `outer`
+/// (outermost first), which spent `self_time` outside of any nested timer.
+pub(crate) fn write_stack<'a>(
+    outer: impl Iterator<Item = &'a str>,
+    name: &'a str,
+    self_time: Duration,
+) {
+    let thread = std::thread::current();
+    let mut line = match thread.name() {
+        Some(name) => frame(name),
+        None => format!("{:?}", thread.id()),
+    };
+    for name in outer.chain(std::iter::once(name)) {
+        line.push(';');
+        line.push_str(&frame(name));
+    }
+    line.push_str(&format!(" {}\n", self_time.as_nanos()));
+    if let Some(out) = out().as_mut() {
+        // There is nowhere sensible to report a failure to write, and timers must not
+        // fail the code they are timing, so errors are ignored.
+        let _ = out.write_all(line.as_bytes());
+    }
+}
+
+// Semicolons separate the frames and a newline ends the line, so neither can appear in a
+// frame's name.
+fn frame(name: &str) -> String {
+    name.replace(';', ":").replace(['\n', '\r'], " ")
+}
@@ -0,0 +1,508 @@
+use crate::{TimerName, TimerTarget};
+use std::fmt::{self, Write};
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+static COMPACT_TARGETS: AtomicBool = AtomicBool::new(false);
+static ELAPSED_FIRST: AtomicBool = AtomicBool::new(false);
+static SEPARATOR: RwLock<&str> = RwLock::new(", ");
+static MAX_EXTRA_INFO_LEN: AtomicUsize = AtomicUsize::new(usize::MAX);
+static RATE_PRECISION: AtomicUsize = AtomicUsize::new(2);
+static GLOBAL_FIELDS: RwLock<Vec<(String, String)>> = RwLock::new(Vec::new());
+
+/// When set to true, timers log with single character targets of `S`, `E`, `F`, `A` and
+/// `Q` instead of `TimerStarting`, `TimerExecuting`, `TimerFinished`, `TimerAbandoned`
+/// and `TimerQueued`.
+/// This saves width in dense terminal output. The default is false, which is better
+/// when the log is going to be parsed.
+pub fn set_compact_targets(enabled: bool) {
+    COMPACT_TARGETS.store(enabled, Ordering::Relaxed);
+}
+
+/// When set to true, messages start with the elapsed time, followed by the name, for
+/// example `Elapsed=28.835275ms, FIND_FILES, Dir=/home`, for log parsers that key on the
+/// first field. Messages without an elapsed time, such as the 'starting' message, still
+/// start with the name. The default is false, which puts the name first. This does not
+/// apply to `MessageFormat::Logfmt`, where the order of the fields does not matter.
+pub fn set_elapsed_first(enabled: bool) {
+    ELAPSED_FIRST.store(enabled, Ordering::Relaxed);
+}
+
+/// Sets the text written between the fields of a message, such as the name, the elapsed
+/// time and the extra info. The default is `", "`, giving messages like
+/// `FIND_FILES, Elapsed=28.835275ms, Dir=/home`. Use `"\t"` for tab-separated output,
+/// for example.
+pub fn set_field_separator(separator: &'static str) {
+    *SEPARATOR.write().unwrap_or_else(|e| e.into_inner()) = separator;
+}
+
+/// Returns the text to write between the fields of a message.
+pub(crate) fn separator() -> &'static str {
+    *SEPARATOR.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Sets the maximum length, in bytes, of the extra information given when a timer is
+/// created, such as `Dir=/home` in `timer!("FIND_FILES", "Dir={}", dir)`. Longer extra
+/// information is cut short, at a character boundary, and `…(truncated)` is appended.
+/// This stops a stray format argument, such as a huge debug dump, from producing
+/// multi-megabyte log lines. `None`, the default, means there is no limit.
+pub fn set_max_extra_info_len(max: Option<usize>) {
+    MAX_EXTRA_INFO_LEN.store(max.unwrap_or(usize::MAX), Ordering::Relaxed);
+}
+
+/// Sets fields which are added to the end of every timer message, such as the name of the
+/// service and the instance it is running on, so that the messages of several instances
+/// can be told apart when they are collected in one place. For example, after
+/// `set_global_timer_fields(&[("Service", "billing"), ("Region", "eu-west-1")])` messages
+/// look like `FIND_FILES, Elapsed=28.835275ms, Service=billing, Region=eu-west-1`. The
+/// fields replace any set previously; pass an empty slice to remove them.
+pub fn set_global_timer_fields(fields: &[(&str, &str)]) {
+    let fields = fields.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+    *GLOBAL_FIELDS.write().unwrap_or_else(|e| e.into_inner()) = fields;
+}
+
+fn global_fields() -> std::sync::RwLockReadGuard<'static, Vec<(String, String)>> {
+    GLOBAL_FIELDS.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Sets the number of decimal places written for rates, such as the `(3000.00/s)` of
+/// `finish_rate!` and the `Throughput=16.80MB/s` of a `StreamTimer`. Use 0 to write
+/// them as whole numbers. The default is 2.
+pub fn set_rate_precision(decimals: usize) {
+    RATE_PRECISION.store(decimals, Ordering::Relaxed);
+}
+
+/// Returns the number of decimal places to write rates with.
+pub(crate) fn rate_precision() -> usize {
+    RATE_PRECISION.load(Ordering::Relaxed)
+}
+
+/// Shortens the extra information of a timer to the maximum length, if it is longer.
+pub(crate) fn truncate_extra_info(mut info: String) -> String {
+    let max = MAX_EXTRA_INFO_LEN.load(Ordering::Relaxed);
+    if info.len() > max {
+        let mut end = max;
+        while !info.is_char_boundary(end) {
+            end -= 1;
+        }
+        info.truncate(end);
+        info.push_str("…(truncated)");
+    }
+    info
+}
+
+/// Returns the log target to use for a message.
+pub(crate) fn target(target: TimerTarget) -> &'static str {
+    if COMPACT_TARGETS.load(Ordering::Relaxed) {
+        match target {
+            TimerTarget::Starting => "S",
+            TimerTarget::Executing => "E",
+            TimerTarget::Finished => "F",
+            TimerTarget::Abandoned => "A",
+            TimerTarget::Queued => "Q",
+        }
+    } else {
+        match target {
+            TimerTarget::Starting => "TimerStarting",
+            TimerTarget::Executing => "TimerExecuting",
+            TimerTarget::Finished => "TimerFinished",
+            TimerTarget::Abandoned => "TimerAbandoned",
+            TimerTarget::Queued => "TimerQueued",
+        }
+    }
+}
+
+/// The format used to write durations, such as the elapsed time, in messages.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DurationFormat {
+    /// The format of `std::time::Duration`'s `Debug` output, for example `28.835275ms` or
+    /// `12.5µs`. This is the default. The durations are written by this crate rather than by
+    /// `Debug`, so the format does not change with the version of Rust, and is as follows.
+    ///
+    /// Durations of a second or more are written in seconds (`s`), otherwise those of a
+    /// millisecond or more in milliseconds (`ms`), otherwise those of a microsecond or more
+    /// in microseconds (`µs`), and anything shorter in whole nanoseconds (`ns`). The number
+    /// of whole units is followed by as many decimal places as are needed to give the
+    /// duration exactly to the nanosecond, with no trailing zeros, and no decimal point if
+    /// there are none; the value is never rounded. So one and a half seconds is `1.5s`,
+    /// `Duration::from_nanos(1_000_001)` is `1.000001ms` and zero is `0ns`.
+    Debug,
+    /// The same as `Debug` but only ever using ASCII characters: microseconds are written
+    /// as `us` rather than `µs`. Use this if your log processing chokes on the micro sign.
+    Ascii,
+    /// The number of nanoseconds as a plain integer, for example `28835275`, which is the
+    /// simplest form to parse. Note that the names of the fields, such as `Elapsed=`, do
+    /// not change, so the consumer must know that this format is in use.
+    Nanos,
+    /// Milliseconds as a fixed-point number with three decimal places, for example
+    /// `28.835ms`. This is written using only integer arithmetic, so it is the cheapest
+    /// format to produce when logging very large numbers of timer messages.
+    Millis,
+}
+
+static DURATION_FORMAT: AtomicU8 = AtomicU8::new(DurationFormat::Debug as u8);
+
+/// Sets the format used to write durations in messages. The default is `DurationFormat::Debug`.
+pub fn set_duration_format(format: DurationFormat) {
+    DURATION_FORMAT.store(format as u8, Ordering::Relaxed);
+}
+
+fn duration_format() -> DurationFormat {
+    match DURATION_FORMAT.load(Ordering::Relaxed) {
+        x if x == DurationFormat::Ascii as u8 => DurationFormat::Ascii,
+        x if x == DurationFormat::Nanos as u8 => DurationFormat::Nanos,
+        x if x == DurationFormat::Millis as u8 => DurationFormat::Millis,
+        _ => DurationFormat::Debug,
+    }
+}
+
+/// The layout of the messages logged by timers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MessageFormat {
+    /// The name followed by comma-separated fields, for example
+    /// `PARSE, Elapsed=28.835275ms, Dir=/home`. This is the default.
+    Standard,
+    /// [logfmt](https://brandur.org/logfmt): space-separated `key=value` pairs, for example
+    /// `timer=PARSE elapsed_ms=28.835 info="Dir=/home" file=src/io.rs line=67 event=finished`.
+    /// Durations are in milliseconds, values containing spaces, `=` or quotes are quoted,
+    /// the extra info is given as `info=` and `finish!` and `executing!` arguments as
+    /// `msg=`. The duration format and field separator settings do not apply.
+    Logfmt,
+}
+
+static MESSAGE_FORMAT: AtomicU8 = AtomicU8::new(MessageFormat::Standard as u8);
+
+/// Sets the layout of the messages logged by timers, for log pipelines that expect
+/// a particular format. The default is `MessageFormat::Standard`. The messages of the
+/// timer statistics are not affected.
+pub fn set_message_format(format: MessageFormat) {
+    MESSAGE_FORMAT.store(format as u8, Ordering::Relaxed);
+}
+
+fn message_format() -> MessageFormat {
+    match MESSAGE_FORMAT.load(Ordering::Relaxed) {
+        x if x == MessageFormat::Logfmt as u8 => MessageFormat::Logfmt,
+        _ => MessageFormat::Standard,
+    }
+}
+
+/// Writes a duration in a `DurationFormat`, or in the global one set by
+/// `set_duration_format` if the format is `None`.
+pub(crate) struct FormattedDuration(pub(crate) Duration, pub(crate) Option<DurationFormat>);
+
+impl fmt::Display for FormattedDuration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.1.unwrap_or_else(duration_format) {
+            DurationFormat::Debug => write_exact(f, self.0, "µs"),
+            DurationFormat::Ascii => write_exact(f, self.0, "us"),
+            DurationFormat::Nanos => write!(f, "{}", self.0.as_nanos()),
+            DurationFormat::Millis => write_fixed_millis(f, self.0, b"ms"),
+        }
+    }
+}
+
+// Writes the duration as milliseconds with three decimal places, followed by `unit`.
+// The digits are produced into a buffer from the right, so that there is only one call
+// to the writer and no padding or precision handling is needed.
+fn write_fixed_millis(f: &mut fmt::Formatter, duration: Duration, unit: &[u8]) -> fmt::Result {
+    // u64::MAX is 20 digits, plus the point and the unit.
+    let mut buf = [0u8; 32];
+    let mut pos = buf.len() - unit.len();
+    buf[pos..].copy_from_slice(unit);
+
+    // u64 arithmetic is much cheaper than u128, and covers over half a million years.
+    let mut micros = duration.as_micros().min(u64::MAX as u128) as u64;
+    for digit in 0.. {
+        if digit == 3 {
+            pos -= 1;
+            buf[pos] = b'.';
+        }
+        pos -= 1;
+        buf[pos] = b'0' + (micros % 10) as u8;
+        micros /= 10;
+        if micros == 0 && digit >= 3 {
+            break;
+        }
+    }
+
+    // Only ASCII digits, '.' and the unit have been written.
+    f.write_str(std::str::from_utf8(&buf[pos..]).map_err(|_| fmt::Error)?)
+}
+
+// Writes the duration in the largest unit of which it is at least one, with as many
+// decimal places as are needed to be exact, as documented on `DurationFormat::Debug`.
+// `micros` is the symbol for microseconds.
+fn write_exact(f: &mut fmt::Formatter, duration: Duration, micros: &str) -> fmt::Result {
+    let secs = duration.as_secs();
+    let nanos = duration.subsec_nanos();
+    // The whole units, the remaining nanoseconds, the value of the first decimal place
+    // in nanoseconds, and the unit.
+    let (whole, mut fraction, mut place, unit) = if secs > 0 {
+        (secs, nanos, 100_000_000, "s")
+    } else if nanos >= 1_000_000 {
+        (u64::from(nanos / 1_000_000), nanos % 1_000_000, 100_000, "ms")
+    } else if nanos >= 1_000 {
+        (u64::from(nanos / 1_000), nanos % 1_000, 100, micros)
+    } else {
+        (u64::from(nanos), 0, 1, "ns")
+    };
+
+    // At most nine decimal places, preceded by the point.
+    let mut decimals = [0u8; 10];
+    let mut len = 0;
+    if fraction > 0 {
+        decimals[0] = b'.';
+        len = 1;
+        while fraction > 0 {
+            decimals[len] = b'0' + (fraction / place) as u8;
+            fraction %= place;
+            place /= 10;
+            len += 1;
+        }
+    }
+
+    // Only ASCII digits and '.' have been written.
+    let decimals = std::str::from_utf8(&decimals[..len]).map_err(|_| fmt::Error)?;
+    write!(f, "{}{}{}", whole, decimals, unit)
+}
+
+/// The text of a timer's message: the name, then any operation id, thread label, tracing
+/// span and span ids, then the elapsed time (except in the 'starting' message), then any
+/// time since the start of the request, parent fraction, wall-clock times, phase breakdown,
+/// extra info, format arguments and global fields, all separated by the field separator.
+/// The elapsed time is moved to the front if `set_elapsed_first` is on.
+pub(crate) struct Message<'a> {
+    pub(crate) target: TimerTarget,
+    pub(crate) file: &'a str,
+    pub(crate) line: u32,
+    pub(crate) name: &'a TimerName<'a>,
+    pub(crate) operation_id: Option<&'a str>,
+    pub(crate) thread_label: Option<&'a str>,
+    pub(crate) tracing_span: Option<u64>,
+    pub(crate) span: Option<(u64, Option<u64>)>,
+    pub(crate) elapsed: Option<Duration>,
+    pub(crate) request_elapsed: Option<Duration>,
+    pub(crate) duration_format: Option<DurationFormat>,
+    pub(crate) parent_fraction: Option<f64>,
+    pub(crate) wall_clock: Option<(SystemTime, Option<SystemTime>)>,
+    pub(crate) cpu: Option<(Duration, Duration)>,
+    pub(crate) queue: Option<(Duration, Duration)>,
+    pub(crate) phases: Option<&'a [(String, Duration)]>,
+    pub(crate) extra_info: Option<&'a str>,
+    pub(crate) args: Option<fmt::Arguments<'a>>,
+}
+
+impl<'a> fmt::Display for Message<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match message_format() {
+            MessageFormat::Standard => self.fmt_standard(f),
+            MessageFormat::Logfmt => self.fmt_logfmt(f),
+        }
+    }
+}
+
+impl<'a> Message<'a> {
+    fn fmt_standard(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sep = separator();
+        let elapsed_first = self.elapsed.filter(|_| ELAPSED_FIRST.load(Ordering::Relaxed));
+        if let Some(elapsed) = elapsed_first {
+            write!(f, "Elapsed={}{}", FormattedDuration(elapsed, self.duration_format), sep)?;
+        }
+        write!(f, "{}", self.name)?;
+        if let Some(operation_id) = self.operation_id {
+            write!(f, "{}OperationId={}", sep, operation_id)?;
+        }
+        if let Some(thread_label) = self.thread_label {
+            write!(f, "{}Thread={}", sep, thread_label)?;
+        }
+        if let Some(tracing_span) = self.tracing_span {
+            write!(f, "{}Span={}", sep, tracing_span)?;
+        }
+        if let Some((span_id, parent_span_id)) = self.span {
+            write!(f, "{}SpanId={}", sep, span_id)?;
+            if let Some(parent_span_id) = parent_span_id {
+                write!(f, "{}ParentSpanId={}", sep, parent_span_id)?;
+            }
+        }
+        if let (Some(elapsed), None) = (self.elapsed, elapsed_first) {
+            write!(f, "{}Elapsed={}", sep, FormattedDuration(elapsed, self.duration_format))?;
+        }
+        if let Some(elapsed) = self.request_elapsed {
+            let elapsed = FormattedDuration(elapsed, self.duration_format);
+            write!(f, "{}RequestElapsed={}", sep, elapsed)?;
+        }
+        if let Some(fraction) = self.parent_fraction {
+            write!(f, "{}ParentFraction={:.1}%", sep, fraction * 100.0)?;
+        }
+        if let Some((start, end)) = self.wall_clock {
+            write!(f, "{}Start={}", sep, WallClock(start))?;
+            if let Some(end) = end {
+                write!(f, "{}End={}", sep, WallClock(end))?;
+            }
+        }
+        if let Some((cpu, blocked)) = self.cpu {
+            write!(f, "{}Cpu={}", sep, FormattedDuration(cpu, self.duration_format))?;
+            write!(f, "{}Blocked={}", sep, FormattedDuration(blocked, self.duration_format))?;
+        }
+        if let Some((wait, run)) = self.queue {
+            write!(f, "{}QueueWait={}", sep, FormattedDuration(wait, self.duration_format))?;
+            write!(f, "{}RunElapsed={}", sep, FormattedDuration(run, self.duration_format))?;
+        }
+        for (name, elapsed) in self.phases.unwrap_or_default() {
+            write!(f, "{}{}={}", sep, name, FormattedDuration(*elapsed, self.duration_format))?;
+        }
+        if let Some(info) = self.extra_info {
+            write!(f, "{}{}", sep, info)?;
+        }
+        if let Some(args) = self.args {
+            write!(f, "{}{}", sep, args)?;
+        }
+        for (key, value) in global_fields().iter() {
+            write!(f, "{}{}={}", sep, key, value)?;
+        }
+        Ok(())
+    }
+
+    fn fmt_logfmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("timer=")?;
+        write_logfmt_value(f, &self.name.to_string())?;
+        if let Some(operation_id) = self.operation_id {
+            f.write_str(" operation_id=")?;
+            write_logfmt_value(f, operation_id)?;
+        }
+        if let Some(thread_label) = self.thread_label {
+            f.write_str(" thread=")?;
+            write_logfmt_value(f, thread_label)?;
+        }
+        if let Some(tracing_span) = self.tracing_span {
+            write!(f, " span={}", tracing_span)?;
+        }
+        if let Some((span_id, parent_span_id)) = self.span {
+            write!(f, " span_id={}", span_id)?;
+            if let Some(parent_span_id) = parent_span_id {
+                write!(f, " parent_span_id={}", parent_span_id)?;
+            }
+        }
+        if let Some(elapsed) = self.elapsed {
+            f.write_str(" elapsed_ms=")?;
+            write_fixed_millis(f, elapsed, b"")?;
+        }
+        if let Some(elapsed) = self.request_elapsed {
+            f.write_str(" request_elapsed_ms=")?;
+            write_fixed_millis(f, elapsed, b"")?;
+        }
+        if let Some(fraction) = self.parent_fraction {
+            write!(f, " parent_pct={:.1}", fraction * 100.0)?;
+        }
+        if let Some((start, end)) = self.wall_clock {
+            write!(f, " start={}", WallClock(start))?;
+            if let Some(end) = end {
+                write!(f, " end={}", WallClock(end))?;
+            }
+        }
+        if let Some((cpu, blocked)) = self.cpu {
+            f.write_str(" cpu_ms=")?;
+            write_fixed_millis(f, cpu, b"")?;
+            f.write_str(" blocked_ms=")?;
+            write_fixed_millis(f, blocked, b"")?;
+        }
+        if let Some((wait, run)) = self.queue {
+            f.write_str(" queue_wait_ms=")?;
+            write_fixed_millis(f, wait, b"")?;
+            f.write_str(" run_ms=")?;
+            write_fixed_millis(f, run, b"")?;
+        }
+        for (name, elapsed) in self.phases.unwrap_or_default() {
+            write!(f, " {}_ms=", name)?;
+            write_fixed_millis(f, *elapsed, b"")?;
+        }
+        if let Some(info) = self.extra_info {
+            f.write_str(" info=")?;
+            write_logfmt_value(f, info)?;
+        }
+        if let Some(args) = self.args {
+            f.write_str(" msg=")?;
+            write_logfmt_value(f, &args.to_string())?;
+        }
+        for (key, value) in global_fields().iter() {
+            write!(f, " {}=", key)?;
+            write_logfmt_value(f, value)?;
+        }
+        f.write_str(" file=")?;
+        write_logfmt_value(f, self.file)?;
+        write!(f, " line={} event={}", self.line, event_name(self.target))
+    }
+}
+
+fn event_name(target: TimerTarget) -> &'static str {
+    match target {
+        TimerTarget::Starting => "starting",
+        TimerTarget::Executing => "executing",
+        TimerTarget::Finished => "finished",
+        TimerTarget::Abandoned => "abandoned",
+        TimerTarget::Queued => "queued",
+    }
+}
+
+// Writes a logfmt value, quoting and escaping it if it is empty or contains anything
+// which would stop it being read back as a single value.
+fn write_logfmt_value(f: &mut fmt::Formatter, value: &str) -> fmt::Result {
+    let needs_quotes = value.is_empty()
+        || value.chars().any(|c| c == ' ' || c == '=' || c == '"' || c.is_control());
+    if !needs_quotes {
+        return f.write_str(value);
+    }
+
+    f.write_char('"')?;
+    for c in value.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+/// Writes a wall-clock time as an RFC 3339 timestamp in UTC with microsecond
+/// precision, for example `2024-03-01T14:05:09.123456Z`.
+pub(crate) struct WallClock(pub(crate) SystemTime);
+
+impl fmt::Display for WallClock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let since_epoch = self.0.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let secs = since_epoch.as_secs();
+        let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+        let secs_of_day = secs % 86_400;
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
+            year,
+            month,
+            day,
+            secs_of_day / 3600,
+            secs_of_day % 3600 / 60,
+            secs_of_day % 60,
+            since_epoch.subsec_micros()
+        )
+    }
+}
+
+// Converts a count of days since 1970-01-01 to a (year, month, day) date in the
+// proleptic Gregorian calendar, using Howard Hinnant's `civil_from_days` algorithm.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month as u32, day as u32)
+}
@@ -0,0 +1,115 @@
+use crate::format::separator;
+use crate::{LoggingTimer, TimerName};
+use std::fmt::{self, Write};
+use std::panic::Location;
+use std::time::Duration;
+
+/// A timer with explicit start and stop calls, for code which would rather not use the
+/// macros. `stop` returns a `FinishBuilder`, to which fields can be added before the
+/// 'finished' message is logged.
+///
+/// The source location logged is that of the `start` call. A handle which is dropped
+/// without being stopped logs its 'finished' message then, like any other timer.
+///
+/// # Examples
+///
+/// ```norun
+/// let handle = TimerHandle::start("IMPORT");
+/// let rows = import(file);
+/// handle.stop().with_field("Rows", rows.len()).with_field("File", file).log();
+/// // Logs "IMPORT, Elapsed=0.5s, Rows=1500, File=users.csv"
+/// ```
+pub struct TimerHandle<'name> {
+    timer: Option<LoggingTimer<'name>>,
+}
+
+impl<'name> TimerHandle<'name> {
+    /// Starts a timer which logs at `Debug` level.
+    #[track_caller]
+    pub fn start(name: impl Into<TimerName<'name>>) -> Self {
+        Self::start_at(::log::Level::Debug, name)
+    }
+
+    /// Starts a timer which logs at `level`.
+    #[track_caller]
+    pub fn start_at(level: ::log::Level, name: impl Into<TimerName<'name>>) -> Self {
+        let location = Location::caller();
+        TimerHandle {
+            timer: LoggingTimer::create(location.file(), None, location.line(), name, None, level),
+        }
+    }
+
+    /// Returns how long the timer has been running for, or `None` if it is disabled.
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.timer.as_ref().map(LoggingTimer::elapsed)
+    }
+
+    /// Stops the timer, fixing its elapsed time, and returns a builder for its 'finished'
+    /// message. Nothing is logged until `FinishBuilder::log` is called or the builder is
+    /// dropped.
+    pub fn stop(mut self) -> FinishBuilder<'name> {
+        let mut timer = self.timer.take();
+        if let Some(timer) = timer.as_mut() {
+            timer.stop();
+        }
+        FinishBuilder { timer, level: None, fields: String::new() }
+    }
+}
+
+/// The 'finished' message of a stopped `TimerHandle`. Fields are added to the message in
+/// the order they are given, as `Key=value`, separated by the field separator. When the
+/// timer is disabled the fields are not formatted.
+pub struct FinishBuilder<'name> {
+    timer: Option<LoggingTimer<'name>>,
+    level: Option<::log::Level>,
+    fields: String,
+}
+
+impl<'name> FinishBuilder<'name> {
+    /// Adds the field `key=value` to the message.
+    pub fn with_field(mut self, key: &str, value: impl fmt::Display) -> Self {
+        if self.timer.is_some() {
+            if !self.fields.is_empty() {
+                self.fields.push_str(separator());
+            }
+            let _ = write!(self.fields, "{}={}", key, value);
+        }
+        self
+    }
+
+    /// Logs the message at `level` rather than at the level of the timer, for example to
+    /// escalate it when the operation has failed.
+    pub fn at_level(mut self, level: ::log::Level) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    /// Logs the 'finished' message.
+    pub fn log(mut self) {
+        self.log_impl();
+    }
+
+    fn log_impl(&mut self) {
+        if let Some(timer) = self.timer.take() {
+            if self.fields.is_empty() {
+                finish(timer, self.level, None);
+            } else {
+                finish(timer, self.level, Some(format_args!("{}", self.fields)));
+            }
+        }
+    }
+}
+
+fn finish(timer: LoggingTimer, level: Option<::log::Level>, args: Option<fmt::Arguments>) {
+    match level {
+        Some(level) => timer.finish_at(level, args),
+        None => timer.finish(args),
+    }
+}
+
+impl<'name> Drop for FinishBuilder<'name> {
+    /// Logs the 'finished' message, if `log` has not been called.
+    fn drop(&mut self) {
+        self.log_impl();
+    }
+}
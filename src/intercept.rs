@@ -0,0 +1,61 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// What should happen to a record after the interceptor has seen it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InterceptAction {
+    /// Pass the record on to the logger as normal.
+    Emit,
+    /// Do not pass the record on; the interceptor has handled it.
+    Suppress,
+}
+
+type Interceptor = Arc<dyn Fn(&::log::Record) -> InterceptAction + Send + Sync>;
+
+static INTERCEPTOR: RwLock<Option<Interceptor>> = RwLock::new(None);
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Installs a function which is called with every `log::Record` built by this crate,
+/// including the timer statistics, just before it is passed to the logger. This gives
+/// complete access to the record (target, level, file, line, module path and message)
+/// for testing or custom integrations. The return value decides whether the record is
+/// then passed to the logger. Installing an interceptor replaces any previous one.
+///
+/// The function may be called from several threads at once, so it must be `Fn`; keep
+/// any state it needs behind a `Mutex`. It is called without any lock held, so it can
+/// safely use timers itself.
+///
+/// # Examples
+///
+/// ```norun
+/// logging_timer::set_record_interceptor(|record| {
+///     metrics::record_timer(record.target(), record.line(), &record.args().to_string());
+///     InterceptAction::Emit
+/// });
+/// ```
+pub fn set_record_interceptor<F>(interceptor: F)
+where
+    F: Fn(&::log::Record) -> InterceptAction + Send + Sync + 'static,
+{
+    *INTERCEPTOR.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(interceptor));
+    ACTIVE.store(true, Ordering::Relaxed);
+}
+
+/// Removes the interceptor installed by `set_record_interceptor`.
+pub fn clear_record_interceptor() {
+    ACTIVE.store(false, Ordering::Relaxed);
+    *INTERCEPTOR.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Passes the record to the interceptor, if there is one, and returns what to do with it.
+pub(crate) fn intercept(record: &::log::Record) -> InterceptAction {
+    if !ACTIVE.load(Ordering::Relaxed) {
+        return InterceptAction::Emit;
+    }
+
+    let interceptor = INTERCEPTOR.read().unwrap_or_else(|e| e.into_inner()).clone();
+    match interceptor {
+        Some(interceptor) => interceptor(record),
+        None => InterceptAction::Emit,
+    }
+}
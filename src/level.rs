@@ -0,0 +1,109 @@
+use log::Level;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// A list of `(Duration, Level)` pairs, sorted by duration.
+pub(crate) type Breakpoints = Vec<(Duration, Level)>;
+
+static BREAKPOINTS: RwLock<Breakpoints> = RwLock::new(Vec::new());
+
+// The most severe level in BREAKPOINTS, as a usize so that it can be checked cheaply
+// when deciding whether to create a timer. 0 means there are no breakpoints.
+static MOST_SEVERE_BREAKPOINT: AtomicUsize = AtomicUsize::new(0);
+
+// The level set by set_min_timer_level, as a usize. 0 means there is none.
+static MIN_LEVEL: AtomicUsize = AtomicUsize::new(0);
+
+/// Sets breakpoints which choose the level of every timer's 'executing' and 'finished'
+/// messages according to the elapsed time. Each `(duration, level)` pair means "if the
+/// elapsed time is at least `duration`, log at `level`"; the pair with the largest
+/// duration not exceeding the elapsed time wins. If the elapsed time is less than every
+/// breakpoint the timer's own level is used. The pairs can be given in any order.
+///
+/// For example, this logs calls under 10ms at trace, under 100ms at debug, under 1s
+/// at info and anything slower at warn:
+///
+/// ```norun
+/// set_level_breakpoints(&[
+///     (Duration::ZERO, Level::Trace),
+///     (Duration::from_millis(10), Level::Debug),
+///     (Duration::from_millis(100), Level::Info),
+///     (Duration::from_secs(1), Level::Warn),
+/// ]);
+/// ```
+///
+/// Since a timer can now log at levels other than its own, timers are created if any
+/// of the breakpoint levels is enabled, not just their own. Pass an empty slice to
+/// remove the breakpoints. Breakpoints can also be set on an individual timer using
+/// `LoggingTimer::with_level_breakpoints`, which take precedence over these.
+pub fn set_level_breakpoints(breakpoints: &[(Duration, Level)]) {
+    let breakpoints = sorted(breakpoints);
+    let most_severe = breakpoints.iter().map(|(_, level)| *level as usize).min().unwrap_or(0);
+    *BREAKPOINTS.write().unwrap_or_else(|e| e.into_inner()) = breakpoints;
+    MOST_SEVERE_BREAKPOINT.store(most_severe, Ordering::Relaxed);
+}
+
+/// Makes every timer log at `level` or a more severe one, whatever level it was given,
+/// so that quiet timers can be made visible for a debugging session without editing the
+/// code. For example, with `Some(Level::Info)` a timer created at `Debug` or `Trace` logs
+/// at `Info`, while one created at `Warn` still logs at `Warn`. Unlike changing `RUST_LOG`,
+/// this changes the level of the timers' messages, so they get through a filter which is
+/// already letting `Info` through. It applies to levels chosen by breakpoints and by
+/// `finish_at` too. Pass `None`, the default, to remove the minimum.
+pub fn set_min_timer_level(level: Option<Level>) {
+    MIN_LEVEL.store(level.map_or(0, |level| level as usize), Ordering::Relaxed);
+}
+
+/// Returns `level`, raised to the minimum set by `set_min_timer_level` if it is less severe.
+#[inline]
+pub(crate) fn floor(level: Level) -> Level {
+    match from_usize(MIN_LEVEL.load(Ordering::Relaxed)) {
+        Some(min) => level.min(min),
+        None => level,
+    }
+}
+
+pub(crate) fn sorted(breakpoints: &[(Duration, Level)]) -> Breakpoints {
+    let mut breakpoints = breakpoints.to_vec();
+    breakpoints.sort_by_key(|(duration, _)| *duration);
+    breakpoints
+}
+
+/// Returns the most severe level that a timer could log at because of the global
+/// breakpoints, if there are any.
+pub(crate) fn most_severe_breakpoint() -> Option<Level> {
+    from_usize(MOST_SEVERE_BREAKPOINT.load(Ordering::Relaxed))
+}
+
+fn from_usize(level: usize) -> Option<Level> {
+    match level {
+        0 => None,
+        1 => Some(Level::Error),
+        2 => Some(Level::Warn),
+        3 => Some(Level::Info),
+        4 => Some(Level::Debug),
+        _ => Some(Level::Trace),
+    }
+}
+
+/// Chooses the level to log at for a timer which has been running for `elapsed`.
+/// The timer's own breakpoints are used if it has any, otherwise the global ones.
+pub(crate) fn level_for(
+    level: Level,
+    timer_breakpoints: Option<&Breakpoints>,
+    elapsed: Duration,
+) -> Level {
+    match timer_breakpoints {
+        Some(breakpoints) => pick(breakpoints, elapsed).unwrap_or(level),
+        None if most_severe_breakpoint().is_some() => {
+            let breakpoints = BREAKPOINTS.read().unwrap_or_else(|e| e.into_inner());
+            pick(&breakpoints, elapsed).unwrap_or(level)
+        }
+        None => level,
+    }
+}
+
+fn pick(breakpoints: &[(Duration, Level)], elapsed: Duration) -> Option<Level> {
+    breakpoints.iter().rev().find(|(duration, _)| *duration <= elapsed).map(|(_, level)| *level)
+}
@@ -34,13 +34,32 @@
 //! #[time("info", "FirstStruct::{}")]      // Logs "FirstStruct::new()" at Info
 //! #[time("info", "SecondStruct::{}")]     // Logs "SecondStruct::new()" at Info
 //! #[time("ThirdStruct::{}")]              // Logs "ThirdStruct::new()" at Debug
+//! #[time("{module}::{fn}")]               // Logs "my_crate::parser::new()" at Debug
 //! #[time("never")]                        // Turn off instrumentation at compile time
 //! ```
 //!
 //! Valid values for the log level are error, warn, info, debug and trace. Debug is the default.
 //! You can also specify 'never' to completely disable the instrumentation at compile time.
 //! The log level should appear first but as shown above can be omitted. The macros distinguish
-//! the log level from the pattern by looking for "{}".
+//! the log level from the pattern by looking for "{}". "{fn}" can be written instead of "{}", and
+//! "{module}" is replaced by the path of the module containing the function.
+//!
+//! For generic functions, the `types` argument names generic type parameters whose concrete
+//! types are included in the timer name, so that each instantiation is timed separately:
+//!
+//! ```norun
+//! #[time(types = "T")]                    // Logs "parse::<u32>()" when called as parse::<u32>()
+//! fn parse<T: FromStr>(input: &str) -> Option<T> { ... }
+//! ```
+//!
+//! For functions that return a `Result`, `on_err` gives a level at which to log the
+//! 'finished' message, including the error, when the function fails. The `result` flag
+//! includes the error without changing the level.
+//!
+//! ```norun
+//! #[time("trace", on_err = "warn")]       // Quiet on success, a warning on failure
+//! fn fetch(url: &str) -> Result<Response, Error> { ... }
+//! ```
 //!
 //!
 //! # Using the Inline Timers
@@ -120,6 +139,46 @@
 //! } // tmr is dropped here but no message is produced.
 //!```
 //!
+//! # Timing Across Scopes
+//!
+//! Sometimes the start and end of the thing you want to time are in different functions,
+//! for example when a job is put on a queue in one place and taken off it somewhere else.
+//! A timer is an ordinary value, so it can be stored and passed around; if you give it an
+//! owned `String` name it borrows nothing and is a `LoggingTimer<'static>`:
+//!
+//! ```norun
+//! use logging_timer::{finish, timer, LoggingTimer};
+//!
+//! struct Job {
+//!     id: u32,
+//!     timer: Option<LoggingTimer<'static>>,
+//! }
+//!
+//! fn enqueue(id: u32) -> Job {
+//!     Job { id, timer: timer!(format!("JOB_{}", id)) }
+//! }
+//!
+//! fn dequeue(job: Job) {
+//!     finish!(job.timer, "Dequeued");
+//! }
+//! ```
+//!
+//! # Outliers and Summaries
+//!
+//! Real profiling usually wants two things at once: to see the slow calls as they happen,
+//! and to understand the distribution of all calls. A threshold and the timer statistics
+//! combine to give this. Calls faster than the threshold are not logged, but they are still
+//! counted in the statistics; slow calls are both logged and counted. The statistics can be
+//! dumped periodically as a summary:
+//!
+//! ```norun
+//! use std::time::Duration;
+//!
+//! logging_timer::set_timer_threshold(Duration::from_millis(100));
+//! logging_timer::enable_timer_stats(true);
+//! logging_timer::set_timer_stats_dump_interval(Some(Duration::from_secs(60)));
+//! ```
+//!
 //! # Setting the log level
 //!
 //! By default both `timer` and `stimer` log at `Debug` level. An optional first parameter to
@@ -152,8 +211,117 @@
 //! instantiated. The module is also set, but is not shown in these examples.
 
 use std::fmt;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::Instant;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+mod active;
+mod aggregate;
+mod anomaly;
+mod backfill;
+#[cfg(feature = "channel")]
+mod channel;
+#[cfg(feature = "chrome-trace")]
+mod chrome_trace;
+mod clock;
+mod compare;
+mod deadline;
+mod deterministic;
+mod error;
+mod finish_hook;
+#[cfg(feature = "flame-graph")]
+mod flame;
+mod format;
+mod handle;
+mod intercept;
+mod level;
+mod mute;
+mod name;
+mod nesting;
+mod operation;
+mod record;
+#[cfg(feature = "replay")]
+mod replay;
+mod request;
+mod retry;
+#[cfg(feature = "ring-buffer")]
+mod ring_buffer;
+mod shared;
+mod silence;
+mod stats;
+mod stderr;
+mod stream;
+mod target_scope;
+#[cfg(feature = "test-util")]
+mod test_util;
+mod thread_label;
+mod threshold;
+mod time_fn;
+mod timer_set;
+mod token;
+
+pub use active::{enable_active_timer_tracking, finish_all_active};
+pub use aggregate::{AggregateScope, aggregate_scope};
+pub use anomaly::set_anomaly_detection;
+pub use backfill::log_timer_span;
+#[cfg(feature = "channel")]
+pub use channel::{WhenFull, clear_timer_channel, set_timer_channel, set_timer_channel_only};
+#[cfg(feature = "chrome-trace")]
+pub use chrome_trace::{finish_chrome_trace, start_chrome_trace};
+pub use clock::{BoottimeClock, Clock, MonotonicClock, SetClockError, set_clock};
+pub use deterministic::set_deterministic_mode;
+pub use error::{TimedError, TimerResultExt};
+pub use finish_hook::{FinishContext, FinishDecision, clear_pre_finish_hook, set_pre_finish_hook};
+#[cfg(feature = "flame-graph")]
+pub use flame::{finish_flame_graph, start_flame_graph};
+pub use format::{
+    DurationFormat, MessageFormat, set_compact_targets, set_duration_format, set_elapsed_first,
+    set_field_separator, set_global_timer_fields, set_max_extra_info_len, set_message_format,
+    set_rate_precision,
+};
+pub use handle::{FinishBuilder, TimerHandle};
+pub use intercept::{InterceptAction, clear_record_interceptor, set_record_interceptor};
+pub use level::{set_level_breakpoints, set_min_timer_level};
+pub use mute::{mute_timer, unmute_timer};
+pub use name::{TimerName, register_timer_name, register_timer_names};
+pub use nesting::{enable_timer_nesting, enable_timer_spans};
+#[cfg(feature = "tokio")]
+pub use operation::with_operation_id;
+pub use operation::{OperationIdGuard, set_operation_id};
+pub use record::TimerRecord;
+#[cfg(feature = "replay")]
+pub use replay::{RecordedTimer, finish_timer_recording, replay_timers, start_timer_recording};
+pub use request::{clear_request_start_source, set_request_start_source};
+#[cfg(feature = "ring-buffer")]
+pub use ring_buffer::{dump_recent_timers, recent_timers, set_recent_timers_capacity};
+pub use shared::SharedTimer;
+pub use silence::{SilenceGuard, silence_timers};
+pub use stats::{
+    TimerStats, dump_timer_stats, enable_timer_stats, get_all_timer_stats, get_timer_stats,
+    reset_timer_stats, set_timer_stats_dump_interval,
+};
+pub use stderr::set_fallback_to_stderr;
+pub use stream::StreamTimer;
+pub use target_scope::{TimerTargetGuard, set_timer_target};
+#[cfg(feature = "test-util")]
+pub use test_util::{captured_timers, clear_captured_timers, init_test_logger};
+pub use thread_label::{clear_thread_timer_label, set_thread_timer_label};
+pub use threshold::set_timer_threshold;
+pub use timer_set::TimerSet;
+pub use token::TimerToken;
+
+#[doc(hidden)]
+pub use compare::__ab_finish;
+#[doc(hidden)]
+pub use error::{__finish_len, __finish_result};
+#[doc(hidden)]
+pub use name::__enclosing_fn_name;
+#[doc(hidden)]
+pub use retry::{__retry_backoff_timed, __retry_timed};
+#[cfg(feature = "test-util")]
+#[doc(hidden)]
+pub use test_util::__assert_timer_logged;
+#[doc(hidden)]
+pub use time_fn::{__time_fn, __time_fn_finish};
 
 // Proc-macros have to be defined in their own lib crate (for now).
 // Re-export them so that users only have to care about this one.
@@ -173,15 +341,24 @@ pub use ::log::Level;
  *   line        =  4
  *   finished    =  1
  *   start_time  = 16
- *   name        = 16
+ *   name        = 24
  *   extra_info  = 24
+ *   options     =  8
  *
- *   TOTAL       = 104
+ *   TOTAL       = 120
  *
  * Wrapping in an Option<T> so that we can avoid most computation if log_enabled!(level)
  * returns false does not increase the size of the value at all. Rust is cool :-)
  */
 
+// Whether a timer has finished is recorded atomically, so that a timer shared between
+// threads logs only one 'finished' message. The `single-threaded` feature makes it a
+// plain `Cell` instead, which is cheaper but makes `LoggingTimer` not `Sync`.
+#[cfg(not(feature = "single-threaded"))]
+type FinishedFlag = std::sync::atomic::AtomicU8;
+#[cfg(feature = "single-threaded")]
+type FinishedFlag = std::cell::Cell<u8>;
+
 /// When this struct is dropped, it logs a message stating its name and how long
 /// the execution time was. Can be used to time functions or other critical areas.
 pub struct LoggingTimer<'name> {
@@ -190,46 +367,243 @@ pub struct LoggingTimer<'name> {
     /// Set by the file!() macro to the name of the file where the timer is instantiated.
     file: &'static str,
     /// Set by the module_path!() macro to the module where the timer is instantiated.
-    module_path: &'static str,
+    /// `None` for timers created by functions, which cannot know their caller's module.
+    module_path: Option<&'static str>,
     /// Set by the line!() macro to the line number where the timer is instantiated.
     line: u32,
-    /// A flag used to suppress printing of the 'Finished' message in the drop() function
-    /// It is set by the finish method.
-    finished: AtomicBool,
-    /// The instant, in UTC, that the timer was instantiated.
+    /// Used to suppress printing of the 'Finished' message in the drop() function.
+    /// Zero until the timer finishes, then the `FinishSource` saying how it finished.
+    finished: FinishedFlag,
+    /// The instant, according to the installed clock, that the timer was instantiated.
     start_time: Instant,
     /// The name of the timer. Used in messages to identify it.
-    name: &'name str,
+    name: TimerName<'name>,
     /// Any extra information to be logged along with the name. Unfortunately, due
     /// to the lifetimes associated with a `format_args!` invocation, this currently allocates
     /// if you use it.
     extra_info: Option<String>,
+    /// Rarely used settings, boxed so that they only cost a pointer when not in use.
+    options: Option<Box<TimerOptions>>,
+}
+
+#[derive(Default)]
+struct TimerOptions {
+    /// Breakpoints which choose the level to log at according to the elapsed time.
+    level_breakpoints: Option<level::Breakpoints>,
+    /// The 'finished' message is not logged if the elapsed time is less than this.
+    threshold: Option<Duration>,
+    /// The operation id in effect when the timer was created.
+    operation_id: Option<Arc<str>>,
+    /// The label of the thread the timer was created on.
+    thread_label: Option<Arc<str>>,
+    /// Arbitrary data for consumers of the timer's records, see `with_context`.
+    context: Option<Arc<dyn std::any::Any + Send + Sync>>,
+    /// The log target set by `set_timer_target` when the timer was created.
+    log_target: Option<Arc<str>>,
+    /// The wall-clock time at which the timer started, if it is to be logged.
+    wall_start: Option<SystemTime>,
+    /// When true the `Elapsed=` field is left out of the messages.
+    hide_elapsed: bool,
+    /// When true the extra info is left out of the 'executing' messages.
+    hide_executing_extra_info: bool,
+    /// The format for durations, overriding the global one.
+    duration_format: Option<DurationFormat>,
+    /// The total elapsed time of each named phase, in order of first use, if the
+    /// breakdown is to be included in the 'finished' message.
+    phases: Option<Mutex<Vec<(String, Duration)>>>,
+    /// The id of the `tracing` span that was current when the timer was created.
+    #[cfg(feature = "tracing")]
+    tracing_span: Option<u64>,
+    /// The thread the timer was started on, in the Chrome trace, if it is being traced.
+    #[cfg(feature = "chrome-trace")]
+    chrome_trace_tid: Option<u32>,
+    /// The id of the timer in the nesting tracker, if nesting is being tracked.
+    nesting_id: Option<u64>,
+    /// The nesting id of the timer that was innermost on this thread when this one started.
+    parent_nesting_id: Option<u64>,
+    /// The thread the timer started on, whose nesting tracker it is in.
+    nesting_thread: Option<std::thread::ThreadId>,
+    /// The thread the timer started on and the CPU time it had used, see `with_cpu_time`.
+    cpu_start: Option<(std::thread::ThreadId, Duration)>,
+    /// The time at which a queued operation started running, see `started`.
+    run_start: Option<Instant>,
+    /// The time at which the timer was stopped by `TimerHandle::stop`, if it has been.
+    stopped_at: Option<Instant>,
+    /// The start time of the request the timer is part of, see `with_request_start`.
+    request_start: Option<Instant>,
+    /// The start time of the ancestor timer to report the elapsed time as a fraction of.
+    parent_start: Option<Instant>,
+    /// The maximum duration the timer is expected to run for.
+    max: Option<Duration>,
+    /// A deadline to report on if the timer runs for longer than it.
+    deadline: Option<deadline::Deadline>,
+    /// The id of the timer in the active timer registry, if all timers are being tracked.
+    active_id: Option<u64>,
 }
 
 impl<'name> LoggingTimer<'name> {
+    /// Returns true if timers at `level` can never be enabled: with the `disabled` feature,
+    /// or when `level` is below log's `STATIC_MAX_LEVEL`. This is a `const fn`, so when it
+    /// is true for the level of a timer macro, the whole expansion folds away to `None`.
+    /// A compiled out timer is not escalated by level breakpoints, counted in the
+    /// statistics or sent to any of the other outputs.
+    #[inline]
+    pub const fn is_compiled_out(level: ::log::Level) -> bool {
+        cfg!(feature = "disabled") || level as usize > ::log::STATIC_MAX_LEVEL as usize
+    }
+
+    /// Returns true if a timer created at `level` would log anything. The timer macros
+    /// call this before evaluating the name and extra format arguments of the timer, so
+    /// that a disabled timer costs nothing more than this check. It is false without any
+    /// runtime check for a level which `is_compiled_out`.
+    #[inline]
+    pub fn is_enabled(level: ::log::Level) -> bool {
+        !Self::is_compiled_out(level)
+            && (Self::level_enabled(level::floor(level))
+                || level::most_severe_breakpoint().is_some_and(Self::level_enabled)
+                || stats::is_enabled()
+                || anomaly::is_enabled()
+                || aggregate::any_active()
+                || Self::channel_active()
+                || Self::ring_buffer_active()
+                || Self::replay_active()
+                || Self::chrome_trace_active()
+                || Self::flame_graph_active())
+    }
+
+    /// Implementation detail of the timer macros, which call this instead of
+    /// `LoggingTimer::new` when `is_enabled` is false. Always returns `None`, after
+    /// writing the hint that no logger is installed if that is the reason.
+    #[doc(hidden)]
+    #[inline]
+    pub fn __disabled(level: ::log::Level) -> Option<Self> {
+        if !Self::is_compiled_out(level) {
+            stderr::hint_if_no_logger();
+        }
+        None
+    }
+
+    #[inline]
+    fn channel_active() -> bool {
+        #[cfg(feature = "channel")]
+        return channel::is_active();
+        #[cfg(not(feature = "channel"))]
+        return false;
+    }
+
+    #[inline]
+    fn channel_exclusive() -> bool {
+        #[cfg(feature = "channel")]
+        return channel::is_exclusive();
+        #[cfg(not(feature = "channel"))]
+        return false;
+    }
+
+    #[inline]
+    fn ring_buffer_active() -> bool {
+        #[cfg(feature = "ring-buffer")]
+        return ring_buffer::is_active();
+        #[cfg(not(feature = "ring-buffer"))]
+        return false;
+    }
+
+    #[inline]
+    fn replay_active() -> bool {
+        #[cfg(feature = "replay")]
+        return replay::is_active();
+        #[cfg(not(feature = "replay"))]
+        return false;
+    }
+
+    #[inline]
+    fn chrome_trace_active() -> bool {
+        #[cfg(feature = "chrome-trace")]
+        return chrome_trace::is_active();
+        #[cfg(not(feature = "chrome-trace"))]
+        return false;
+    }
+
+    #[inline]
+    fn flame_graph_active() -> bool {
+        #[cfg(feature = "flame-graph")]
+        return flame::is_active();
+        #[cfg(not(feature = "flame-graph"))]
+        return false;
+    }
+
+    /// Returns true if a message at exactly `level` would be output somewhere.
+    #[inline]
+    fn level_enabled(level: ::log::Level) -> bool {
+        ::log::log_enabled!(level) || stderr::fallback_active()
+    }
+
     /// Constructs a new `LoggingTimer` that prints only a 'TimerFinished' message.
-    /// This method is not usually called directly, use the `timer!` macro instead.
+    /// This method is not usually called directly, use the `timer!` macro instead,
+    /// or `timer_at!` if you need to supply the source location yourself.
+    ///
+    /// With the `tracing` feature, if a `tracing` span is current when the timer is
+    /// created, its id is included in the timer's messages as `Span=<id>`, tying them
+    /// back to the surrounding trace.
     pub fn new(
         file: &'static str,
         module_path: &'static str,
         line: u32,
-        name: &'name str,
+        name: impl Into<TimerName<'name>>,
+        extra_info: Option<String>,
+        level: ::log::Level,
+    ) -> Option<Self> {
+        Self::create(file, Some(module_path), line, name, extra_info, level)
+    }
+
+    /// Constructs a new `LoggingTimer` for the functions and methods which start timers
+    /// on behalf of their caller, and so have no module path to give it.
+    pub(crate) fn create(
+        file: &'static str,
+        module_path: Option<&'static str>,
+        line: u32,
+        name: impl Into<TimerName<'name>>,
         extra_info: Option<String>,
         level: ::log::Level,
     ) -> Option<Self> {
-        if ::log::log_enabled!(level) {
-            Some(LoggingTimer {
+        let name = name.into();
+        if Self::is_enabled(level) && !mute::is_muted(&name) {
+            let mut tmr = LoggingTimer {
                 level,
-                start_time: Instant::now(),
+                start_time: clock::now(),
                 file,
                 module_path,
                 line,
                 name,
-                finished: AtomicBool::new(false),
-                extra_info,
-            })
+                finished: FinishedFlag::new(0),
+                extra_info: extra_info.map(format::truncate_extra_info),
+                options: operation::current().map(|operation_id| {
+                    Box::new(TimerOptions {
+                        operation_id: Some(operation_id),
+                        ..Default::default()
+                    })
+                }),
+            };
+
+            if let Some(label) = thread_label::current() {
+                tmr.options_mut().thread_label = Some(label);
+            }
+            if let Some(target) = target_scope::current() {
+                tmr.options_mut().log_target = Some(target);
+            }
+            let tmr = tmr.track_nesting();
+            let tmr = match request::current() {
+                Some(start) => tmr.with_request_start(start),
+                None => tmr,
+            };
+            let tmr = tmr.track_active();
+            #[cfg(feature = "chrome-trace")]
+            let tmr = tmr.begin_chrome_trace();
+            #[cfg(feature = "tracing")]
+            let tmr = tmr.capture_tracing_span();
+
+            Some(tmr)
         } else {
-            None
+            Self::__disabled(level)
         }
     }
 
@@ -239,22 +613,60 @@ impl<'name> LoggingTimer<'name> {
         file: &'static str,
         module_path: &'static str,
         line: u32,
-        name: &'name str,
+        name: impl Into<TimerName<'name>>,
         extra_info: Option<String>,
         level: ::log::Level,
     ) -> Option<Self> {
-        if ::log::log_enabled!(level) {
-            let tmr = Self::new(file, module_path, line, name, extra_info, level).unwrap();
-            tmr.log_impl(TimerTarget::Starting, None);
-            Some(tmr)
-        } else {
-            None
-        }
+        let tmr = Self::new(file, module_path, line, name, extra_info, level)?;
+        tmr.log_impl(TimerTarget::Starting, None, None);
+        Some(tmr)
     }
 
     /// Returns how long the timer has been running for.
     pub fn elapsed(&self) -> std::time::Duration {
-        self.start_time.elapsed()
+        match self.options.as_ref().and_then(|o| o.stopped_at) {
+            Some(stopped_at) => stopped_at.saturating_duration_since(self.start_time),
+            None => clock::elapsed_since(self.start_time),
+        }
+    }
+
+    /// Stops the clock of the timer, so that the elapsed time no longer increases.
+    pub(crate) fn stop(&mut self) {
+        let now = clock::now();
+        self.options_mut().stopped_at = Some(now);
+    }
+
+    /// Returns the name of the timer.
+    pub fn name(&self) -> &TimerName<'name> {
+        &self.name
+    }
+
+    /// Changes the name of the timer, for when the meaningful name is only known once the
+    /// operation is under way, such as after parsing the type of a request. The messages
+    /// logged from now on, including the 'finished' message, use the new name, and the
+    /// timer is counted in the statistics under it. Renaming a timer which has already
+    /// finished has no effect.
+    ///
+    /// ```norun
+    /// let mut tmr = timer!("REQUEST");
+    /// let request = parse(bytes);
+    /// if let Some(tmr) = tmr.as_mut() {
+    ///     tmr.rename(format!("REQUEST_{}", request.kind()));
+    /// }
+    /// ```
+    pub fn rename(&mut self, name: impl Into<TimerName<'name>>) {
+        if self.is_finished() {
+            return;
+        }
+        self.name = name.into();
+        if let Some(options) = self.options.as_ref() {
+            if let (Some(id), Some(thread)) = (options.nesting_id, options.nesting_thread) {
+                nesting::rename(id, thread, self.name.to_string());
+            }
+            if let Some(id) = options.active_id {
+                active::rename(id, self.name.to_string());
+            }
+        }
     }
 
     /// Sets the logging level.
@@ -272,292 +684,1318 @@ impl<'name> LoggingTimer<'name> {
         self
     }
 
-    /// Outputs a log message with a target of 'TimerExecuting' showing the current elapsed time, but does not
-    /// stop the timer. This method can be called multiple times.
-    /// The message can include further information via a `format_args!` approach.
-    /// This method is usually not called directly, it is easier to use the `executing!` macro.
-    pub fn executing(&self, args: Option<fmt::Arguments>) {
-        self.log_impl(TimerTarget::Executing, args);
+    /// Sets breakpoints which choose the level of this timer's 'executing' and 'finished'
+    /// messages according to the elapsed time, overriding any set by `set_level_breakpoints`.
+    /// See that function for how the breakpoints are interpreted.
+    /// Note that this consumes self, so that it can be called in a one-liner like this:
+    ///
+    /// ```norun
+    /// let tmr = timer!("foo").map(|t| t.with_level_breakpoints(&[(Duration::from_secs(1), Level::Warn)]));
+    /// ```
+    pub fn with_level_breakpoints(mut self, breakpoints: &[(Duration, ::log::Level)]) -> Self {
+        self.options_mut().level_breakpoints = Some(level::sorted(breakpoints));
+        self
     }
 
-    /// Outputs a log message with a target of 'TimerFinished' and suppresses the normal message
-    /// that is output when the timer is dropped. The message can include further `format_args!`
-    /// information. This method is normally called using the `finish!` macro. Calling
-    /// `finish()` again will have no effect.
-    pub fn finish(&self, args: Option<fmt::Arguments>) {
-        if !self.finished.load(Ordering::SeqCst) {
-            self.finished.store(true, Ordering::SeqCst);
-            self.log_impl(TimerTarget::Finished, args);
-        }
+    /// Sets a threshold below which this timer does not log its 'finished' message,
+    /// overriding any set by `set_timer_threshold`. The call is still counted in the
+    /// timer statistics. Like `with_level_breakpoints`, this consumes self.
+    pub fn with_threshold(mut self, threshold: Duration) -> Self {
+        self.options_mut().threshold = Some(threshold);
+        self
     }
 
-    fn log_impl(&self, target: TimerTarget, args: Option<fmt::Arguments>) {
-        if !::log::log_enabled!(self.level) {
-            return;
-        }
+    /// Attaches arbitrary data to the timer, which the standard message format ignores but
+    /// which is passed on in every `TimerRecord` the timer produces, for a consumer of the
+    /// records to downcast with `TimerRecord::context` and render in its own way. This lets
+    /// an integration enrich the timer output with structured, typed data without this
+    /// crate knowing its shape.
+    ///
+    /// ```norun
+    /// let tmr = timer!("HANDLE").map(|t| t.with_context(RequestInfo { route, user_id }));
+    /// ```
+    pub fn with_context<C: std::any::Any + Send + Sync>(mut self, context: C) -> Self {
+        self.options_mut().context = Some(Arc::new(context));
+        self
+    }
 
-        match (target, self.extra_info.as_ref(), args) {
-            (TimerTarget::Starting, Some(info), Some(args)) => {
-                self.log_record(target, format_args!("{}, {}, {}", self.name, info, args))
-            }
-            (TimerTarget::Starting, Some(info), None) => {
-                self.log_record(target, format_args!("{}, {}", self.name, info))
-            }
-            (TimerTarget::Starting, None, Some(args)) => {
-                self.log_record(target, format_args!("{}, {}", self.name, args))
-            }
-            (TimerTarget::Starting, None, None) => {
-                self.log_record(target, format_args!("{}", self.name))
-            }
+    /// Returns the data attached by `with_context`, if there is any and it is of type `C`.
+    pub fn context<C: std::any::Any>(&self) -> Option<&C> {
+        self.options.as_ref()?.context.as_deref()?.downcast_ref()
+    }
 
-            (_, Some(info), Some(args)) => self.log_record(
-                target,
-                format_args!("{}, Elapsed={:?}, {}, {}", self.name, self.elapsed(), info, args),
-            ),
-            (_, Some(info), None) => self.log_record(
-                target,
-                format_args!("{}, Elapsed={:?}, {}", self.name, self.elapsed(), info),
-            ),
-            (_, None, Some(args)) => self.log_record(
-                target,
-                format_args!("{}, Elapsed={:?}, {}", self.name, self.elapsed(), args),
-            ),
-            (_, None, None) => {
-                self.log_record(target, format_args!("{}, Elapsed={:?}", self.name, self.elapsed()))
-            }
-        };
+    /// Measures the CPU time used by the current thread while the timer runs, so that the
+    /// 'finished' message includes it as `Cpu=`, and the rest of the elapsed time, when
+    /// the thread was not running, as `Blocked=`. A large `Blocked` time means the code
+    /// spent most of its time waiting, for IO or a lock say, rather than computing. The
+    /// CPU time is only measured on Linux, and only if the timer finishes on the thread
+    /// that created it, so it should not be used for timers that cross an `.await`.
+    ///
+    /// ```norun
+    /// let tmr = timer!("LOAD").map(|t| t.with_cpu_time());
+    /// // Logs "LOAD, Elapsed=100ms, Cpu=10ms, Blocked=90ms"
+    /// ```
+    pub fn with_cpu_time(mut self) -> Self {
+        if let Some(cpu) = clock::thread_cpu_time() {
+            self.options_mut().cpu_start = Some((std::thread::current().id(), cpu));
+        }
+        self
     }
 
-    fn log_record(&self, target: TimerTarget, args: fmt::Arguments) {
-        ::log::logger().log(
-            &log::RecordBuilder::new()
-                .level(self.level)
-                .target(match target {
-                    TimerTarget::Starting => "TimerStarting",
-                    TimerTarget::Executing => "TimerExecuting",
-                    TimerTarget::Finished => "TimerFinished",
-                })
-                .file(Some(self.file))
-                .module_path(Some(self.module_path))
-                .line(Some(self.line))
-                .args(args)
-                .build(),
-        );
+    /// Records the wall-clock time at which the timer started, so that the 'executing'
+    /// and 'finished' messages include a `Start=` timestamp, and the 'finished' message an
+    /// `End=` timestamp, for correlating with logs from other systems. The timestamps are
+    /// written in RFC 3339 format in UTC. The elapsed time is still measured with the
+    /// monotonic clock, and `End` is always `Start` plus the elapsed time so the two agree.
+    /// `stimer!` has already logged its 'starting' message by the time this can be called,
+    /// so use `stimer!(name; wall_clock)` for that message to include `Start=` too.
+    ///
+    /// ```norun
+    /// let tmr = timer!("foo").map(|t| t.with_wall_clock());
+    /// let tmr = stimer!("foo"; wall_clock);
+    /// ```
+    pub fn with_wall_clock(mut self) -> Self {
+        let now = SystemTime::now();
+        let start = now.checked_sub(self.elapsed()).unwrap_or(now);
+        self.options_mut().wall_start = Some(start);
+        self
     }
-}
 
-impl<'a> Drop for LoggingTimer<'a> {
-    /// Drops the timer, outputting a log message with a target of `TimerFinished`
-    /// if the `finish` method has not yet been called.
-    fn drop(&mut self) {
-        self.finish(None);
+    /// Leaves the `Elapsed=` field out of this timer's 'executing' and 'finished' messages,
+    /// so that they only contain the name and your own text. This is useful if you embed
+    /// your own timing in the message. The elapsed time is still used to choose the level
+    /// and for the threshold and the timer statistics.
+    ///
+    /// ```norun
+    /// let tmr = timer!("foo").map(|t| t.without_elapsed());
+    /// ```
+    pub fn without_elapsed(mut self) -> Self {
+        self.options_mut().hide_elapsed = true;
+        self
     }
-}
 
-#[derive(Debug, Copy, Clone)]
-enum TimerTarget {
-    Starting,
-    Executing,
-    Finished,
-}
+    /// Sets whether this timer's 'executing' messages include the extra information given
+    /// when the timer was created, as well as their own format arguments. The default is
+    /// true; set it to false for briefer intermediate messages in a long-running timer.
+    /// The 'starting' and 'finished' messages always include the extra information.
+    ///
+    /// ```norun
+    /// let tmr = timer!("foo", "Loading {}", path).map(|t| t.with_executing_extra_info(false));
+    /// executing!(tmr, "Loaded {} rows", rows);   // Logs "foo, Elapsed=.., Loaded 10 rows"
+    /// ```
+    pub fn with_executing_extra_info(mut self, include: bool) -> Self {
+        self.options_mut().hide_executing_extra_info = !include;
+        self
+    }
 
-/* TODO: These macro definitions are very verbose, especially the duplication to get
- * 'level' to work, but after much hacking this was the only combination I could
- * get to work. There is probably a way to reduce the duplication, especially
- * by making the 'level' bit optional.
- */
+    /// Sets a deadline for the timer. If the timer runs for longer than `deadline` a warning
+    /// stating `DeadlineExceeded=` is logged, in addition to the normal messages. With the
+    /// `deadline` feature a background thread logs the warning the moment the deadline
+    /// passes, so that breaches are visible in real time even if the operation never
+    /// completes; without it, or in deterministic mode, the warning is logged when the timer
+    /// finishes. Deadlines are watched in real time, even if a `Clock` has been installed.
+    ///
+    /// This is more conveniently done via the `deadline =` form of the `timer!` and
+    /// `stimer!` macros.
+    ///
+    /// ```norun
+    /// let tmr = timer!("foo"; deadline = Duration::from_secs(5));
+    /// ```
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        let deadline = deadline::Deadline::new(deadline);
+        #[cfg(feature = "deadline")]
+        let deadline = {
+            let mut deadline = deadline;
+            if !deterministic::is_enabled() {
+                let name = self.name.to_string();
+                deadline::watch(&mut deadline, self.location(), name, self.elapsed());
+            }
+            deadline
+        };
+        self.options_mut().deadline = Some(deadline);
+        self
+    }
 
-/// Creates a timer that does not log a starting message, only a finished one.
-///
-/// # Examples
-/// Note that when specifying the log level you must use a semi-colon as a
-/// separator, this is to ensure disambiguous parsing of the macro arguments.
-///
-/// ```norun
-///
-/// use logging_timer::{stime, time, stimer, timer, Level};
-///
-/// let _tmr1 = timer!("FIND_FILES");
-/// let _tmr2 = timer!(Level::Info; "FIND_FILES");
-/// let _tmr3 = timer!("FIND_FILES", "Found {} files", 42);
-/// let _tmr4 = timer!(Level::Trace; "FIND_FILES", "Found {} files", 42);
-/// ```
-#[macro_export]
-macro_rules! timer {
-    ($name:expr) => {
-        {
-            $crate::LoggingTimer::new(
-                file!(),
-                module_path!(),
-                line!(),
-                $name,
-                None,
-                ::logging_timer::Level::Debug,
-                )
-        }
-    };
+    /// Records the elapsed time of each phase started by `phase` and includes a breakdown
+    /// of them in the 'finished' message, for example
+    /// `Elapsed=35ms, parse=10ms, validate=5ms, write=20ms`. Phases with the same name,
+    /// such as those in a loop, are added together.
+    ///
+    /// ```norun
+    /// let tmr = timer!("foo").map(|t| t.with_phase_breakdown());
+    /// ```
+    pub fn with_phase_breakdown(mut self) -> Self {
+        self.options_mut().phases = Some(Mutex::new(Vec::new()));
+        self
+    }
 
-    ($level:expr; $name:expr) => {
-        {
-            $crate::LoggingTimer::new(
-                file!(),
-                module_path!(),
-                line!(),
-                $name,
-                None,
-                $level,
-                )
+    fn record_phase(&self, name: &str, elapsed: Duration) {
+        if let Some(phases) = self.options.as_ref().and_then(|o| o.phases.as_ref()) {
+            let mut phases = phases.lock().unwrap_or_else(|e| e.into_inner());
+            match phases.iter_mut().find(|(n, _)| n == name) {
+                Some((_, total)) => *total += elapsed,
+                None => phases.push((name.to_string(), elapsed)),
+            }
         }
-    };
+    }
 
-    ($name:expr, $format:tt) => {
-        {
-            $crate::LoggingTimer::new(
-                file!(),
-                module_path!(),
-                line!(),
-                $name,
-                Some(format!($format)),
-                ::logging_timer::Level::Debug,
-                )
-        }
-    };
+    /// Sets the format used to write durations in this timer's messages, overriding
+    /// the one set by `set_duration_format`.
+    ///
+    /// ```norun
+    /// let tmr = timer!("foo").map(|t| t.with_duration_format(DurationFormat::Nanos));
+    /// ```
+    pub fn with_duration_format(mut self, format: DurationFormat) -> Self {
+        self.options_mut().duration_format = Some(format);
+        self
+    }
 
-    ($level:expr; $name:expr, $format:tt) => {
-        {
-            $crate::LoggingTimer::new(
-                file!(),
-                module_path!(),
-                line!(),
-                $name,
-                Some(format!($format)),
-                $level,
-                )
-        }
-    };
+    pub(crate) fn duration_format(&self) -> Option<DurationFormat> {
+        self.options.as_ref().and_then(|o| o.duration_format)
+    }
 
-    ($name:expr, $format:tt, $($arg:expr),*) => {
-        {
-            $crate::LoggingTimer::new(
-                file!(),
-                module_path!(),
-                line!(),
-                $name,
-                Some(format!($format, $($arg), *)),
-                ::logging_timer::Level::Debug,
-                )
-        }
-    };
+    /// Sets the maximum duration this timer is expected to run for. If the timer runs for
+    /// longer, an error stating `MaxExceeded=` is logged when it finishes, with a target of
+    /// `TimerExecuting` so that the timer still logs only one 'finished' message. With the
+    /// `strict-timing` feature it panics instead, which fails tests and CI runs fast on
+    /// unexpected slowness. Enable that feature only in your dev-dependencies, so that in
+    /// production the check is just an error log. A timer that is dropped while the thread
+    /// is already panicking never panics again.
+    ///
+    /// This is more conveniently done via the `max =` form of the `timer!` and `stimer!`
+    /// macros.
+    ///
+    /// ```norun
+    /// let tmr = timer!("foo"; max = Duration::from_secs(10));
+    /// ```
+    pub fn with_max(mut self, max: Duration) -> Self {
+        self.options_mut().max = Some(max);
+        self
+    }
 
-    ($level:expr; $name:expr, $format:tt, $($arg:expr),*) => {
-        {
-            $crate::LoggingTimer::new(
-                file!(),
-                module_path!(),
-                line!(),
-                $name,
-                Some(format!($format, $($arg), *)),
-                $level,
-                )
-        }
-    };
-}
+    fn check_max(&self, elapsed: Duration) {
+        let max = match self.options.as_ref().and_then(|o| o.max) {
+            Some(max) if elapsed > max => max,
+            _ => return,
+        };
 
-/// Creates a timer that logs a starting mesage and a finished message.
-///
-/// # Examples
-/// Note that when specifying the log level you must use a semi-colon as a
-/// separator, this is to ensure disambiguous parsing of the macro arguments.
-///
-/// ```norun
-///
-/// use logging_timer::{stime, time, stimer, timer, Level};
-///
-/// let _tmr1 = stimer!("FIND_FILES");
-/// let _tmr2 = stimer!(Level::Info; "FIND_FILES");
-/// let _tmr3 = stimer!("FIND_FILES", "Found {} files", 42);
-/// let _tmr4 = stimer!(Level::Trace; "FIND_FILES", "Found {} files", 42);
-/// ```
-#[macro_export]
-macro_rules! stimer {
-    ($name:expr) => {
-        {
-            $crate::LoggingTimer::with_start_message(
-                file!(),
-                module_path!(),
-                line!(),
-                $name,
-                None,
-                ::logging_timer::Level::Debug,
-                )
+        let format = self.duration_format();
+        if Self::level_enabled(::log::Level::Error) {
+            let sep = format::separator();
+            self.log_record(
+                ::log::Level::Error,
+                TimerTarget::Executing,
+                Some(elapsed),
+                format_args!(
+                    "{}{}Elapsed={}{}MaxExceeded={}",
+                    self.name,
+                    sep,
+                    format::FormattedDuration(elapsed, format),
+                    sep,
+                    format::FormattedDuration(max, format)
+                ),
+            );
         }
-    };
 
-    ($level:expr; $name:expr) => {
-        {
-            $crate::LoggingTimer::with_start_message(
-                file!(),
-                module_path!(),
-                line!(),
-                $name,
-                None,
-                $level,
-                )
+        if cfg!(feature = "strict-timing") && !std::thread::panicking() {
+            panic!(
+                "Timer {} ran for {}, exceeding its maximum of {}",
+                self.name,
+                format::FormattedDuration(elapsed, format),
+                format::FormattedDuration(max, format)
+            );
         }
-    };
+    }
 
-    ($level:expr; $name:expr, $format:tt) => {
-        {
-            $crate::LoggingTimer::with_start_message(
-                file!(),
-                module_path!(),
-                line!(),
-                $name,
-                Some(format!($format)),
-                $level,
-                )
-        }
-    };
+    fn location(&self) -> deadline::Location {
+        deadline::Location { file: self.file, module_path: self.module_path, line: self.line }
+    }
 
-    ($name:expr, $format:tt) => {
-        {
-            $crate::LoggingTimer::with_start_message(
-                file!(),
-                module_path!(),
-                line!(),
-                $name,
-                Some(format!($format)),
-                ::logging_timer::Level::Debug,
-                )
-        }
-    };
+    /// Returns the wall-clock time at which the timer started, if `with_wall_clock`
+    /// has been called.
+    pub fn wall_start(&self) -> Option<SystemTime> {
+        self.options.as_ref().and_then(|o| o.wall_start)
+    }
 
-    ($name:expr, $format:tt, $($arg:expr),*) => {
-        {
-            $crate::LoggingTimer::with_start_message(
-                file!(),
-                module_path!(),
-                line!(),
-                $name,
-                Some(format!($format, $($arg), *)),
-                ::logging_timer::Level::Debug,
-                )
+    fn track_nesting(mut self) -> Self {
+        if nesting::is_enabled() {
+            let (id, parent_id) = nesting::push(self.name.to_string(), self.start_time);
+            let options = self.options_mut();
+            options.nesting_id = Some(id);
+            options.parent_nesting_id = parent_id;
+            options.nesting_thread = Some(std::thread::current().id());
         }
-    };
+        self
+    }
 
-    ($level:expr; $name:expr, $format:tt, $($arg:expr),*) => {
-        {
-            $crate::LoggingTimer::with_start_message(
-                file!(),
-                module_path!(),
-                line!(),
-                $name,
-                Some(format!($format, $($arg), *)),
-                $level,
-                )
+    fn track_active(mut self) -> Self {
+        if active::is_enabled() {
+            let id = active::register(
+                self.name.to_string(),
+                self.level,
+                self.extra_info.clone(),
+                self.start_time,
+                self.location(),
+            );
+            self.options_mut().active_id = Some(id);
         }
-    };
-}
+        self
+    }
+
+    /// Makes this timer's messages include a `RequestElapsed=` field, the time since
+    /// `start`, alongside the timer's own elapsed time. `start` is usually the time at
+    /// which the request or job that this timer is a part of started, and should be taken
+    /// from the same clock as the timer. This is done automatically for every timer if a
+    /// source has been installed with `set_request_start_source`.
+    ///
+    /// ```norun
+    /// let tmr = timer!("QUERY").map(|t| t.with_request_start(request.received_at));
+    /// ```
+    pub fn with_request_start(mut self, start: Instant) -> Self {
+        self.options_mut().request_start = Some(start);
+        self
+    }
+
+    /// Links this timer to the innermost running timer called `name` on the current thread,
+    /// so that the 'finished' message includes this timer's elapsed time as a percentage of
+    /// the ancestor's elapsed time so far, e.g. `ParentFraction=35.0%`. This shows the
+    /// relative cost of the parts of a call hierarchy. It requires the nesting of timers
+    /// to be tracked, see `enable_timer_nesting`; if there is no such timer running,
+    /// nothing is added to the message.
+    ///
+    /// ```norun
+    /// let tmr = timer!("PARSE").map(|t| t.with_parent("REQUEST"));
+    /// ```
+    pub fn with_parent(mut self, name: &str) -> Self {
+        let nesting_id = self.options.as_ref().and_then(|o| o.nesting_id);
+        if let Some(start) = nesting::find_start_time(name, nesting_id) {
+            self.options_mut().parent_start = Some(start);
+        }
+        self
+    }
+
+    #[cfg(feature = "chrome-trace")]
+    fn begin_chrome_trace(mut self) -> Self {
+        if chrome_trace::is_active() {
+            let tid = chrome_trace::current_tid();
+            self.options_mut().chrome_trace_tid = Some(tid);
+        }
+        self
+    }
+
+    #[cfg(feature = "tracing")]
+    fn capture_tracing_span(mut self) -> Self {
+        if let Some(id) = ::tracing::Span::current().id() {
+            self.options_mut().tracing_span = Some(id.into_u64());
+        }
+        self
+    }
+
+    #[inline]
+    fn tracing_span(&self) -> Option<u64> {
+        #[cfg(feature = "tracing")]
+        return self.options.as_ref().and_then(|o| o.tracing_span);
+        #[cfg(not(feature = "tracing"))]
+        return None;
+    }
+
+    /// Returns the CPU time used since the timer started, if it is being measured and
+    /// this is the thread it started on.
+    fn cpu_elapsed(&self) -> Option<Duration> {
+        let (thread, start) = self.options.as_ref()?.cpu_start?;
+        if thread != std::thread::current().id() {
+            return None;
+        }
+        Some(clock::thread_cpu_time()?.saturating_sub(start))
+    }
+
+    fn options_mut(&mut self) -> &mut TimerOptions {
+        self.options.get_or_insert_with(Default::default)
+    }
+
+    /// Outputs a log message with a target of 'TimerExecuting' showing the current elapsed time, but does not
+    /// stop the timer. This method can be called multiple times.
+    /// The message can include further information via a `format_args!` approach.
+    /// This method is usually not called directly, it is easier to use the `executing!` macro.
+    pub fn executing(&self, args: Option<fmt::Arguments>) {
+        self.log_impl(TimerTarget::Executing, None, args);
+    }
+
+    /// Marks the point at which an operation that was queued starts running, for a timer
+    /// that was created when the operation was queued. A message with a target of
+    /// `TimerQueued` is logged, whose elapsed time is the time spent waiting in the queue,
+    /// and the 'finished' message splits the elapsed time into the `QueueWait` and the
+    /// `RunElapsed`, so that queue latency can be told apart from processing latency.
+    /// Only the first call has any effect.
+    /// This method is usually not called directly, it is easier to use the `started!` macro.
+    pub fn started(&mut self) {
+        if self.options.as_ref().is_some_and(|o| o.run_start.is_some()) {
+            return;
+        }
+        self.options_mut().run_start = Some(clock::now());
+        self.log_impl(TimerTarget::Queued, None, None);
+    }
+
+    /// Starts a named phase within this timer. An 'executing' message is logged now, and
+    /// another one, which also includes the elapsed time of the phase itself, is logged
+    /// when the returned guard is dropped. This gives scoped timing of the distinct phases
+    /// of an operation without having to call `executing!` by hand.
+    /// This method is usually not called directly, it is easier to use the `phase!` macro.
+    pub fn phase<'a>(&'a self, name: &'a str) -> PhaseGuard<'a, 'name> {
+        self.executing(Some(format_args!("Phase={}", name)));
+        PhaseGuard { timer: self, name, start_time: clock::now() }
+    }
+
+    /// Outputs a log message with a target of 'TimerFinished' and suppresses the normal message
+    /// that is output when the timer is dropped. The message can include further `format_args!`
+    /// information. This method is normally called using the `finish!` macro. Calling
+    /// `finish()` again will have no effect.
+    ///
+    /// If the elapsed time is below the timer's threshold the message is not logged,
+    /// but the call is still recorded in the timer statistics.
+    pub fn finish(&self, args: Option<fmt::Arguments>) {
+        self.finish_impl(FinishSource::Explicit, None, args);
+    }
+
+    /// Finishes the timer with a message stating how many things were processed and the
+    /// rate at which they were processed, e.g. `Processed 1500 rows (3000.00/s)`. If no time
+    /// has elapsed the rate is given as `inf`. This method is normally called using the
+    /// `finish_rate!` macro.
+    pub fn finish_rate(&self, count: u64, unit: &str) {
+        let secs = self.elapsed().as_secs_f64();
+        if secs > 0.0 {
+            let rate = count as f64 / secs;
+            let precision = format::rate_precision();
+            self.finish(Some(format_args!(
+                "Processed {} {} ({:.*}/s)",
+                count, unit, precision, rate
+            )));
+        } else {
+            self.finish(Some(format_args!("Processed {} {} (inf/s)", count, unit)));
+        }
+    }
+
+    /// The same as `finish`, except that the 'finished' message is logged at `level`
+    /// rather than at the timer's own level. This is for escalating the message when the
+    /// operation has failed, for example.
+    pub fn finish_at(&self, level: ::log::Level, args: Option<fmt::Arguments>) {
+        self.finish_impl(FinishSource::Explicit, Some(level), args);
+    }
+
+    /// Sets the level of the timer. Used by `#[time(on_err = ...)]`, which may have
+    /// to create the timer at the error level in order to create it at all.
+    #[doc(hidden)]
+    pub fn __with_level(mut self, level: ::log::Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Logs the 'starting' message of a timer created without one. Used by `stimer!` to
+    /// set options which the 'starting' message includes before logging it.
+    #[doc(hidden)]
+    pub fn __with_start_message(self) -> Self {
+        self.log_impl(TimerTarget::Starting, None, None);
+        self
+    }
+
+    /// Returns true if the timer has finished.
+    #[inline]
+    fn is_finished(&self) -> bool {
+        #[cfg(not(feature = "single-threaded"))]
+        return self.finished.load(std::sync::atomic::Ordering::SeqCst) != 0;
+        #[cfg(feature = "single-threaded")]
+        return self.finished.get() != 0;
+    }
+
+    /// Records how the timer finished, returning false if it had already finished.
+    #[inline]
+    fn mark_finished(&self, source: FinishSource) -> bool {
+        #[cfg(not(feature = "single-threaded"))]
+        return {
+            use std::sync::atomic::Ordering;
+            self.finished
+                .compare_exchange(0, source as u8, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+        };
+        #[cfg(feature = "single-threaded")]
+        return if self.finished.get() == 0 {
+            self.finished.set(source as u8);
+            true
+        } else {
+            false
+        };
+    }
+
+    /// The single path by which a timer finishes, whether explicitly or by being dropped.
+    /// Only the first call has any effect. `level`, if given, overrides the level of the
+    /// 'finished' message.
+    fn finish_impl(
+        &self,
+        source: FinishSource,
+        level: Option<::log::Level>,
+        args: Option<fmt::Arguments>,
+    ) {
+        if self.mark_finished(source) {
+            let elapsed = self.elapsed();
+            // A timer already reported by `finish_all_active` does not log again.
+            let abandoned = self
+                .options
+                .as_ref()
+                .and_then(|o| o.active_id)
+                .is_some_and(|id| !active::unregister(id));
+            stats::record(&self.name, elapsed);
+            aggregate::record(&self.name, elapsed);
+            let anomalous = anomaly::check(&self.name, elapsed);
+            if let Some(o) = self.options.as_ref() {
+                if let (Some(id), Some(thread)) = (o.nesting_id, o.nesting_thread) {
+                    nesting::remove(id, thread, elapsed);
+                }
+            }
+            #[cfg(feature = "chrome-trace")]
+            if let Some(tid) = self.options.as_ref().and_then(|o| o.chrome_trace_tid) {
+                chrome_trace::complete(
+                    &self.name,
+                    self.extra_info.as_deref(),
+                    tid,
+                    self.start_time,
+                    elapsed,
+                );
+            }
+            if let Some(deadline) = self.options.as_ref().and_then(|o| o.deadline.as_ref()) {
+                deadline.finish(self.location(), &self.name, elapsed);
+            }
+            let timer_threshold = self.options.as_ref().and_then(|o| o.threshold);
+            if !abandoned {
+                if anomalous {
+                    self.log_anomaly(elapsed, level, args);
+                } else if !threshold::is_below(timer_threshold, elapsed) {
+                    self.log_finished(elapsed, level, args);
+                } else if Self::ring_buffer_active() {
+                    // Fast calls are kept out of the live log, but a dump of the ring buffer
+                    // after a failure should still show everything that led up to it.
+                    self.log_to(TimerTarget::Finished, level, args, false);
+                }
+            }
+            self.check_max(elapsed);
+        }
+    }
+
+    /// Logs the 'finished' message of a call which was unusually slow for this timer, at
+    /// `Warn` level or more severe, with `Anomaly=true` added.
+    fn log_anomaly(
+        &self,
+        elapsed: Duration,
+        level: Option<::log::Level>,
+        args: Option<fmt::Arguments>,
+    ) {
+        let level = level.unwrap_or_else(|| {
+            let breakpoints = self.options.as_ref().and_then(|o| o.level_breakpoints.as_ref());
+            level::level_for(self.level, breakpoints, elapsed)
+        });
+        let level = Some(level.min(::log::Level::Warn));
+        match args {
+            Some(args) => self.log_finished(
+                elapsed,
+                level,
+                Some(format_args!("{}{}Anomaly=true", args, format::separator())),
+            ),
+            None => self.log_finished(elapsed, level, Some(format_args!("Anomaly=true"))),
+        }
+    }
+
+    /// Logs the 'finished' message, after asking the pre-finish hook whether to.
+    fn log_finished(
+        &self,
+        elapsed: Duration,
+        level: Option<::log::Level>,
+        args: Option<fmt::Arguments>,
+    ) {
+        let breakpoints = self.options.as_ref().and_then(|o| o.level_breakpoints.as_ref());
+        let context = FinishContext {
+            name: &self.name,
+            level: level::floor(
+                level.unwrap_or_else(|| level::level_for(self.level, breakpoints, elapsed)),
+            ),
+            elapsed,
+            extra_info: self.extra_info.as_deref(),
+            file: self.file,
+            module_path: self.module_path,
+            line: self.line,
+        };
+
+        match finish_hook::decide(&context) {
+            FinishDecision::Emit => self.log_impl(TimerTarget::Finished, level, args),
+            FinishDecision::Suppress => {}
+            FinishDecision::EmitWith(extra) => match args {
+                Some(args) => self.log_impl(
+                    TimerTarget::Finished,
+                    level,
+                    Some(format_args!("{}{}{}", args, format::separator(), extra)),
+                ),
+                None => {
+                    self.log_impl(TimerTarget::Finished, level, Some(format_args!("{}", extra)))
+                }
+            },
+        }
+    }
+
+    fn log_impl(
+        &self,
+        target: TimerTarget,
+        level: Option<::log::Level>,
+        args: Option<fmt::Arguments>,
+    ) {
+        self.log_to(target, level, args, true);
+    }
+
+    /// Produces a message of the timer, sending it to every output if `live` is true, or
+    /// only to the ring buffer if it is false.
+    fn log_to(
+        &self,
+        target: TimerTarget,
+        level: Option<::log::Level>,
+        args: Option<fmt::Arguments>,
+        live: bool,
+    ) {
+        if silence::is_silenced() {
+            return;
+        }
+
+        let elapsed = self.elapsed();
+        let level = match (level, target) {
+            (Some(level), _) => level,
+            (None, TimerTarget::Starting) => self.level,
+            _ => {
+                let breakpoints = self.options.as_ref().and_then(|o| o.level_breakpoints.as_ref());
+                level::level_for(self.level, breakpoints, elapsed)
+            }
+        };
+        let level = level::floor(level);
+
+        let send = live && Self::channel_active();
+        let keep = Self::ring_buffer_active();
+        let record = live && Self::replay_active();
+        let log = live && Self::level_enabled(level) && !Self::channel_exclusive();
+        if !send && !keep && !record && !log {
+            return;
+        }
+
+        let wall_clock = self.options.as_ref().and_then(|o| o.wall_start).map(|start| {
+            let end = match target {
+                TimerTarget::Finished => Some(start + elapsed),
+                _ => None,
+            };
+            (start, end)
+        });
+
+        let phases = match target {
+            TimerTarget::Finished => self.options.as_ref().and_then(|o| o.phases.as_ref()),
+            _ => None,
+        }
+        .map(|phases| phases.lock().unwrap_or_else(|e| e.into_inner()));
+
+        let message = format::Message {
+            target,
+            file: self.file,
+            line: self.line,
+            name: &self.name,
+            operation_id: self.options.as_ref().and_then(|o| o.operation_id.as_deref()),
+            thread_label: self.options.as_ref().and_then(|o| o.thread_label.as_deref()),
+            tracing_span: self.tracing_span(),
+            span: match self.options.as_ref() {
+                Some(o) if nesting::spans_enabled() => {
+                    o.nesting_id.map(|id| (id, o.parent_nesting_id))
+                }
+                _ => None,
+            },
+            elapsed: match target {
+                TimerTarget::Starting => None,
+                _ if self.options.as_ref().is_some_and(|o| o.hide_elapsed) => None,
+                _ => Some(elapsed),
+            },
+            request_elapsed: self
+                .options
+                .as_ref()
+                .and_then(|o| o.request_start)
+                .map(clock::elapsed_since),
+            duration_format: self.duration_format(),
+            parent_fraction: match target {
+                TimerTarget::Finished => {
+                    self.options.as_ref().and_then(|o| o.parent_start).map(|start| {
+                        let parent_elapsed = clock::elapsed_since(start);
+                        elapsed.as_secs_f64() / parent_elapsed.as_secs_f64().max(f64::MIN_POSITIVE)
+                    })
+                }
+                _ => None,
+            },
+            wall_clock,
+            cpu: match target {
+                TimerTarget::Finished => {
+                    self.cpu_elapsed().map(|cpu| (cpu, elapsed.saturating_sub(cpu)))
+                }
+                _ => None,
+            },
+            queue: match target {
+                TimerTarget::Finished => {
+                    self.options.as_ref().and_then(|o| o.run_start).map(|run_start| {
+                        let wait = run_start.saturating_duration_since(self.start_time);
+                        (wait, elapsed.saturating_sub(wait))
+                    })
+                }
+                _ => None,
+            },
+            phases: phases.as_deref().map(Vec::as_slice),
+            extra_info: match target {
+                TimerTarget::Executing
+                    if self.options.as_ref().is_some_and(|o| o.hide_executing_extra_info) =>
+                {
+                    None
+                }
+                _ => self.extra_info.as_deref(),
+            },
+            args,
+        };
+
+        #[cfg(feature = "channel")]
+        if send {
+            channel::send(self.timer_record(target, level, elapsed, &message));
+        }
+
+        #[cfg(feature = "ring-buffer")]
+        if keep {
+            ring_buffer::push(self.timer_record(target, level, elapsed, &message));
+        }
+
+        #[cfg(feature = "replay")]
+        if record {
+            replay::push(replay::RecordedTimer {
+                target,
+                level,
+                name: self.name.to_string(),
+                start_offset: replay::start_offset(self.start_time),
+                elapsed: message.elapsed,
+                extra_info: message.extra_info.map(str::to_string),
+                args: message.args.map(|args| args.to_string()),
+                file: self.file.to_string(),
+                module_path: self.module_path.map(str::to_string),
+                line: self.line,
+            });
+        }
+
+        if log {
+            #[cfg(feature = "test-util")]
+            test_util::capture(self.timer_record(target, level, elapsed, &message));
+            self.log_record(level, target, message.elapsed, format_args!("{}", message));
+        }
+    }
+
+    #[cfg(any(feature = "channel", feature = "ring-buffer", feature = "test-util"))]
+    fn timer_record(
+        &self,
+        target: TimerTarget,
+        level: ::log::Level,
+        elapsed: Duration,
+        message: &format::Message,
+    ) -> TimerRecord {
+        TimerRecord {
+            target,
+            level,
+            name: self.name.to_string(),
+            elapsed: match target {
+                TimerTarget::Starting => None,
+                _ => Some(elapsed),
+            },
+            message: message.to_string(),
+            file: self.file,
+            module_path: self.module_path,
+            line: self.line,
+            context: self.options.as_ref().and_then(|o| o.context.clone()),
+        }
+    }
+
+    /// Logs a message of the timer. With the `kv` feature the record also carries the
+    /// name of the timer and the elapsed time, in whole nanoseconds, as the structured
+    /// fields `timer` and `elapsed_ns`, so that log pipelines can treat the elapsed time
+    /// as a number rather than parsing it out of the message.
+    fn log_record(
+        &self,
+        level: ::log::Level,
+        target: TimerTarget,
+        elapsed: Option<Duration>,
+        args: fmt::Arguments,
+    ) {
+        let log_target = self.options.as_ref().and_then(|o| o.log_target.as_deref());
+        let mut builder = log::RecordBuilder::new();
+        builder
+            .level(level)
+            .target(log_target.unwrap_or_else(|| format::target(target)))
+            .file(Some(self.file))
+            .module_path(self.module_path)
+            .line(Some(self.line))
+            .args(args);
+
+        #[cfg(feature = "kv")]
+        let key_values = [
+            Some(("timer", ::log::kv::Value::from_display(&self.name))),
+            elapsed.map(|elapsed| {
+                let nanos = elapsed.as_nanos().min(u64::MAX as u128) as u64;
+                ("elapsed_ns", ::log::kv::Value::from(nanos))
+            }),
+        ];
+        #[cfg(feature = "kv")]
+        builder.key_values(&key_values);
+
+        // Only the colors of the stderr fallback depend on whether the call was slow.
+        let slow = cfg!(feature = "color")
+            && target == TimerTarget::Finished
+            && elapsed.is_some_and(|elapsed| {
+                threshold::is_reached(self.options.as_ref().and_then(|o| o.threshold), elapsed)
+            });
+
+        emit_timer(&builder.build(), slow);
+    }
+}
+
+/// Outputs a record to the logger, or to stderr if falling back to it, after passing
+/// it to any interceptor.
+pub(crate) fn emit(record: &::log::Record) {
+    emit_timer(record, false);
+}
+
+/// As `emit`, for a timer's own message, `slow` being true if the call reached its
+/// threshold.
+fn emit_timer(record: &::log::Record, slow: bool) {
+    if intercept::intercept(record) == InterceptAction::Suppress {
+        return;
+    }
+
+    if stderr::fallback_active() {
+        stderr::write_record(record, slow);
+    } else {
+        ::log::logger().log(record);
+    }
+}
+
+impl<'a> Drop for LoggingTimer<'a> {
+    /// Drops the timer, outputting a log message with a target of `TimerFinished`
+    /// if the `finish` method has not yet been called.
+    fn drop(&mut self) {
+        let source =
+            if std::thread::panicking() { FinishSource::Panic } else { FinishSource::Drop };
+        self.finish_impl(source, None, None);
+    }
+}
+
+/// A guard representing a phase within a `LoggingTimer`, created by `LoggingTimer::phase`
+/// or the `phase!` macro. When it is dropped the timer logs an 'executing' message stating
+/// the elapsed time of the phase.
+pub struct PhaseGuard<'a, 'name> {
+    timer: &'a LoggingTimer<'name>,
+    name: &'a str,
+    start_time: Instant,
+}
+
+impl<'a, 'name> PhaseGuard<'a, 'name> {
+    /// Returns how long the phase has been running for.
+    pub fn elapsed(&self) -> std::time::Duration {
+        clock::elapsed_since(self.start_time)
+    }
+}
+
+impl<'a, 'name> Drop for PhaseGuard<'a, 'name> {
+    fn drop(&mut self) {
+        let elapsed = self.elapsed();
+        self.timer.record_phase(self.name, elapsed);
+        self.timer.executing(Some(format_args!(
+            "Phase={}{}PhaseElapsed={}",
+            self.name,
+            format::separator(),
+            format::FormattedDuration(elapsed, self.timer.duration_format())
+        )));
+    }
+}
+
+/// How a timer came to be finished.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+enum FinishSource {
+    /// By a call to `finish`.
+    Explicit = 1,
+    /// By being dropped at the end of its scope.
+    Drop = 2,
+    /// By being dropped while the thread is unwinding from a panic.
+    Panic = 3,
+}
+
+/// Which of a timer's messages is being produced. These correspond to the log targets
+/// `TimerStarting`, `TimerExecuting`, `TimerFinished`, `TimerAbandoned` and `TimerQueued`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum TimerTarget {
+    /// The message logged when a timer created by `stimer!` starts.
+    Starting,
+    /// A message logged by `executing!` or a phase.
+    Executing,
+    /// The message logged when a timer finishes.
+    Finished,
+    /// The message logged by `finish_all_active` for a timer that has not finished.
+    Abandoned,
+    /// The message logged by `started` when a queued operation starts running.
+    Queued,
+}
+
+/* TODO: These macro definitions are very verbose, especially the duplication to get
+ * 'level' to work, but after much hacking this was the only combination I could
+ * get to work. There is probably a way to reduce the duplication, especially
+ * by making the 'level' bit optional.
+ */
+
+/// Implementation detail of the `timer!` and `stimer!` macros. The level is checked
+/// before anything else is evaluated, so when the level is disabled the name and
+/// format arguments are never evaluated and no allocation takes place.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __timer_new {
+    ($ctor:ident, $level:expr, $name:expr, $extra_info:expr) => {
+        $crate::__timer_new!(@at file!(), module_path!(), line!(); $ctor, $level, $name, $extra_info)
+    };
+
+    (@at $file:expr, $module_path:expr, $line:expr; $ctor:ident, $level:expr, $name:expr, $extra_info:expr) => {{
+        let level = $level;
+        if $crate::LoggingTimer::is_enabled(level) {
+            $crate::LoggingTimer::$ctor($file, $module_path, $line, $name, $extra_info, level)
+        } else {
+            $crate::LoggingTimer::__disabled(level)
+        }
+    }};
+}
+
+/// Creates a timer that does not log a starting message, only a finished one.
+///
+/// # Examples
+/// Note that when specifying the log level you must use a semi-colon as a
+/// separator, this is to ensure disambiguous parsing of the macro arguments.
+///
+/// ```norun
+///
+/// use logging_timer::{stime, time, stimer, timer, Level};
+///
+/// let _tmr1 = timer!("FIND_FILES");
+/// let _tmr2 = timer!(Level::Info; "FIND_FILES");
+/// let _tmr3 = timer!("FIND_FILES", "Found {} files", 42);
+/// let _tmr4 = timer!(Level::Trace; "FIND_FILES", "Found {} files", 42);
+/// let _tmr5 = timer!("FIND_FILES"; deadline = Duration::from_secs(5));
+/// let _tmr6 = timer!("FIND_FILES"; max = Duration::from_secs(10));
+/// ```
+///
+/// # Overhead When Disabled
+///
+/// The file, module path, line number and level are all compile-time constants.
+/// The level is checked first, and if it is disabled the macro evaluates to `None`
+/// without evaluating the name or the format arguments.
+///
+/// If the level is below log's `STATIC_MAX_LEVEL` (see the `max_level_*` and
+/// `release_max_level_*` features of the log crate), or the `disabled` feature is on,
+/// the check is the constant `LoggingTimer::is_compiled_out` and the whole expansion
+/// folds away to `None`. Otherwise the check is made at runtime: an atomic load and a
+/// call to the logger's `enabled` method for the level, and then, if that is disabled,
+/// an atomic load for each of the other things that enable timers regardless of the
+/// level, such as level breakpoints, the statistics and the trace outputs, and one more
+/// for the hint that no logger is installed. Reading the clock and formatting the extra
+/// information only happen when the timer is actually enabled.
+#[macro_export]
+macro_rules! timer {
+    ($name:expr; deadline = $deadline:expr) => {
+        $crate::timer!($name).map(|t| t.with_deadline($deadline))
+    };
+
+    ($level:expr; $name:expr; deadline = $deadline:expr) => {
+        $crate::timer!($level; $name).map(|t| t.with_deadline($deadline))
+    };
+
+    ($name:expr; max = $max:expr) => {
+        $crate::timer!($name).map(|t| t.with_max($max))
+    };
+
+    ($level:expr; $name:expr; max = $max:expr) => {
+        $crate::timer!($level; $name).map(|t| t.with_max($max))
+    };
+
+    ($name:expr) => {
+        $crate::__timer_new!(new, ::logging_timer::Level::Debug, $name, None)
+    };
+
+    ($level:expr; $name:expr) => {
+        $crate::__timer_new!(new, $level, $name, None)
+    };
+
+    ($name:expr, $format:tt) => {
+        $crate::__timer_new!(new, ::logging_timer::Level::Debug, $name, Some(format!($format)))
+    };
+
+    ($level:expr; $name:expr, $format:tt) => {
+        $crate::__timer_new!(new, $level, $name, Some(format!($format)))
+    };
+
+    ($name:expr, $format:tt, $($arg:expr),*) => {
+        $crate::__timer_new!(
+            new,
+            ::logging_timer::Level::Debug,
+            $name,
+            Some(format!($format, $($arg), *))
+        )
+    };
+
+    ($level:expr; $name:expr, $format:tt, $($arg:expr),*) => {
+        $crate::__timer_new!(new, $level, $name, Some(format!($format, $($arg), *)))
+    };
+}
+
+/// Creates a timer that logs a starting mesage and a finished message.
+///
+/// # Examples
+/// Note that when specifying the log level you must use a semi-colon as a
+/// separator, this is to ensure disambiguous parsing of the macro arguments.
+///
+/// ```norun
+///
+/// use logging_timer::{stime, time, stimer, timer, Level};
+///
+/// let _tmr1 = stimer!("FIND_FILES");
+/// let _tmr2 = stimer!(Level::Info; "FIND_FILES");
+/// let _tmr3 = stimer!("FIND_FILES", "Found {} files", 42);
+/// let _tmr4 = stimer!(Level::Trace; "FIND_FILES", "Found {} files", 42);
+/// let _tmr5 = stimer!("FIND_FILES"; deadline = Duration::from_secs(5));
+/// let _tmr6 = stimer!("FIND_FILES"; max = Duration::from_secs(10));
+/// let _tmr7 = stimer!("FIND_FILES"; wall_clock);
+/// ```
+#[macro_export]
+macro_rules! stimer {
+    ($name:expr; wall_clock) => {
+        $crate::timer!($name).map(|t| t.with_wall_clock().__with_start_message())
+    };
+
+    ($level:expr; $name:expr; wall_clock) => {
+        $crate::timer!($level; $name).map(|t| t.with_wall_clock().__with_start_message())
+    };
+
+    ($name:expr; deadline = $deadline:expr) => {
+        $crate::stimer!($name).map(|t| t.with_deadline($deadline))
+    };
+
+    ($level:expr; $name:expr; deadline = $deadline:expr) => {
+        $crate::stimer!($level; $name).map(|t| t.with_deadline($deadline))
+    };
+
+    ($name:expr; max = $max:expr) => {
+        $crate::stimer!($name).map(|t| t.with_max($max))
+    };
+
+    ($level:expr; $name:expr; max = $max:expr) => {
+        $crate::stimer!($level; $name).map(|t| t.with_max($max))
+    };
+
+    ($name:expr) => {
+        $crate::__timer_new!(with_start_message, ::logging_timer::Level::Debug, $name, None)
+    };
+
+    ($level:expr; $name:expr) => {
+        $crate::__timer_new!(with_start_message, $level, $name, None)
+    };
+
+    ($name:expr, $format:tt) => {
+        $crate::__timer_new!(
+            with_start_message,
+            ::logging_timer::Level::Debug,
+            $name,
+            Some(format!($format))
+        )
+    };
+
+    ($level:expr; $name:expr, $format:tt) => {
+        $crate::__timer_new!(with_start_message, $level, $name, Some(format!($format)))
+    };
+
+    ($name:expr, $format:tt, $($arg:expr),*) => {
+        $crate::__timer_new!(
+            with_start_message,
+            ::logging_timer::Level::Debug,
+            $name,
+            Some(format!($format, $($arg), *))
+        )
+    };
+
+    ($level:expr; $name:expr, $format:tt, $($arg:expr),*) => {
+        $crate::__timer_new!(with_start_message, $level, $name, Some(format!($format, $($arg), *)))
+    };
+}
+
+/// The same as `timer!`, but logging the given source location instead of the location of
+/// the macro call. This is for code generators, whose output would otherwise report the
+/// location of the generated code, which is rarely meaningful to the reader of the log.
+/// The file and module path must be `&'static str`, typically string literals, and the
+/// line a `u32`. They are given first, separated from the usual arguments by a semi-colon.
+///
+/// A build script that writes timed code into `OUT_DIR` can pass the location of the
+/// item it was generated from, so that the log points at the real source:
+///
+/// ```norun
+/// // Written by build.rs into $OUT_DIR/handlers.rs, generated from line 12 of api.yaml.
+/// pub fn get_user(id: u32) -> User {
+///     let _tmr = logging_timer::timer_at!("api.yaml", "api::get_user", 12; "GET_USER");
+///     // ...
+/// }
+/// ```
+///
+/// Your own `macro_rules!` macros can forward `file!()`, `module_path!()` and `line!()`
+/// from their caller in the same way.
+#[macro_export]
+macro_rules! timer_at {
+    ($file:expr, $module_path:expr, $line:expr; $name:expr) => {
+        $crate::__timer_new!(@at $file, $module_path, $line; new, $crate::Level::Debug, $name, None)
+    };
+
+    ($file:expr, $module_path:expr, $line:expr; $level:expr; $name:expr) => {
+        $crate::__timer_new!(@at $file, $module_path, $line; new, $level, $name, None)
+    };
+
+    ($file:expr, $module_path:expr, $line:expr; $name:expr, $format:tt $(, $arg:expr)*) => {
+        $crate::__timer_new!(
+            @at $file, $module_path, $line;
+            new,
+            $crate::Level::Debug,
+            $name,
+            Some(format!($format $(, $arg)*))
+        )
+    };
+
+    ($file:expr, $module_path:expr, $line:expr; $level:expr; $name:expr, $format:tt $(, $arg:expr)*) => {
+        $crate::__timer_new!(
+            @at $file, $module_path, $line;
+            new,
+            $level,
+            $name,
+            Some(format!($format $(, $arg)*))
+        )
+    };
+}
+
+/// The same as `stimer!`, but logging the given source location instead of the location
+/// of the macro call. See `timer_at!`.
+#[macro_export]
+macro_rules! stimer_at {
+    ($file:expr, $module_path:expr, $line:expr; $name:expr) => {
+        $crate::__timer_new!(
+            @at $file, $module_path, $line;
+            with_start_message,
+            $crate::Level::Debug,
+            $name,
+            None
+        )
+    };
+
+    ($file:expr, $module_path:expr, $line:expr; $level:expr; $name:expr) => {
+        $crate::__timer_new!(@at $file, $module_path, $line; with_start_message, $level, $name, None)
+    };
+
+    ($file:expr, $module_path:expr, $line:expr; $name:expr, $format:tt $(, $arg:expr)*) => {
+        $crate::__timer_new!(
+            @at $file, $module_path, $line;
+            with_start_message,
+            $crate::Level::Debug,
+            $name,
+            Some(format!($format $(, $arg)*))
+        )
+    };
+
+    ($file:expr, $module_path:expr, $line:expr; $level:expr; $name:expr, $format:tt $(, $arg:expr)*) => {
+        $crate::__timer_new!(
+            @at $file, $module_path, $line;
+            with_start_message,
+            $level,
+            $name,
+            Some(format!($format $(, $arg)*))
+        )
+    };
+}
+
+/// Creates a timer only the first time this line of code is executed with its level
+/// enabled; every later execution returns `None`, so nothing is logged. Executions before
+/// that, such as those before a logger is installed, do not count. This is for logging the
+/// cold start of lazily-initialised code while ignoring the warm calls that follow. Each
+/// call site has its own flag, shared by all threads, though threads which make the first
+/// call at the same moment may each get a timer. The arguments are the same as for `timer!`.
+///
+/// # Examples
+///
+/// ```norun
+///
+/// use logging_timer::{timer_once, Level};
+///
+/// fn lookup(key: &str) -> Option<&Value> {
+///     let _tmr = timer_once!(Level::Info; "LOOKUP", "first lookup");
+///     TABLE.get_or_init(load_table).get(key)
+/// }
+/// ```
+#[macro_export]
+macro_rules! timer_once {
+    ($($arg:tt)*) => {{
+        static FIRST_CALL: ::std::sync::atomic::AtomicBool = ::std::sync::atomic::AtomicBool::new(true);
+        if FIRST_CALL.load(::std::sync::atomic::Ordering::Relaxed) {
+            let tmr = $crate::timer!($($arg)*);
+            if tmr.is_some() {
+                FIRST_CALL.store(false, ::std::sync::atomic::Ordering::Relaxed);
+            }
+            tmr
+        } else {
+            None
+        }
+    }};
+}
+
+/// Creates a `StreamTimer` for a streaming operation on a known number of bytes. Call
+/// `advance` on it as data is processed and it periodically logs the progress, throughput
+/// and estimated time remaining. The log level is optional, as for `timer!`.
+///
+/// # Examples
+///
+/// ```norun
+///
+/// use logging_timer::{stream_timer, Level};
+///
+/// let mut tmr = stream_timer!(Level::Info; "DOWNLOAD", content_length)
+///     .with_update_every_bytes(Some(64 * 1024 * 1024));
+/// while let Some(chunk) = response.chunk()? {
+///     file.write_all(&chunk)?;
+///     tmr.advance(chunk.len() as u64);
+/// }
+/// ```
+#[macro_export]
+macro_rules! stream_timer {
+    ($level:expr; $name:expr, $total_bytes:expr) => {
+        $crate::StreamTimer::new($crate::timer!($level; $name), $total_bytes)
+    };
+
+    ($name:expr, $total_bytes:expr) => {
+        $crate::StreamTimer::new($crate::timer!($name), $total_bytes)
+    };
+}
+
+/// Creates a `TimerToken`, a `Send` record of a started timer which can be handed to
+/// another thread and finished there. It takes the same arguments as `timer!`, and
+/// returns `None` if the level is disabled. The name must be `'static`, such as a string
+/// literal or a `String`.
+///
+/// # Examples
+///
+/// ```norun
+/// use logging_timer::{timer_token, Level};
+///
+/// let token = timer_token!(Level::Info; "REQUEST", "Id={}", id);
+/// sender.send((request, token))?;
+///
+/// // On the worker thread.
+/// let (request, token) = receiver.recv()?;
+/// let response = handle(request);
+/// if let Some(token) = token {
+///     token.finish(Some(format_args!("Status={}", response.status)));
+/// }
+/// ```
+#[macro_export]
+macro_rules! timer_token {
+    ($name:expr) => {
+        $crate::timer_token!($crate::Level::Debug; $name)
+    };
+
+    ($level:expr; $name:expr) => {{
+        let level = $level;
+        if $crate::LoggingTimer::is_enabled(level) {
+            $crate::TimerToken::new(file!(), module_path!(), line!(), $name, None, level)
+        } else {
+            None
+        }
+    }};
+
+    ($name:expr, $format:tt $(, $arg:expr)*) => {
+        $crate::timer_token!($crate::Level::Debug; $name, $format $(, $arg)*)
+    };
+
+    ($level:expr; $name:expr, $format:tt $(, $arg:expr)*) => {{
+        let level = $level;
+        if $crate::LoggingTimer::is_enabled(level) {
+            let extra_info = Some(format!($format $(, $arg)*));
+            $crate::TimerToken::new(file!(), module_path!(), line!(), $name, extra_info, level)
+        } else {
+            None
+        }
+    }};
+}
+
+/// Creates a `SharedTimer`, a timer handle which is cloned into many places, such as the
+/// items of a fan-out, and which logs one summary of all the clones when the last of them
+/// is dropped. The log level is optional, as for `timer!`.
+///
+/// # Examples
+///
+/// ```norun
+/// use logging_timer::{shared_timer, Level};
+///
+/// let tmr = shared_timer!(Level::Info; "PROCESS_ALL");
+/// items.par_iter().for_each(|item| {
+///     let _tmr = tmr.clone();
+///     process(item);
+/// });
+/// drop(tmr);  // Logs "PROCESS_ALL, Elapsed=1.2s, Count=500, Total=..., Mean=..., Min=..., Max=..."
+/// ```
+#[macro_export]
+macro_rules! shared_timer {
+    ($level:expr; $name:expr) => {
+        $crate::SharedTimer::new($crate::timer!($level; $name))
+    };
+
+    ($name:expr) => {
+        $crate::SharedTimer::new($crate::timer!($name))
+    };
+}
+
+/// Creates a timer that is named by a numeric id rather than a string. This is
+/// otherwise identical to `timer!`, and accepts the same optional log level and
+/// extra format arguments.
+///
+/// The id is turned into a name only when a message is logged, using the names
+/// registered via `register_timer_name`. Unregistered ids are logged as `#<id>`.
+///
+/// # Examples
+///
+/// ```norun
+///
+/// use logging_timer::{register_timer_names, timer_id, Level};
+///
+/// const PARSE: u32 = 1;
+/// register_timer_names(&[(PARSE, "PARSE")]);
+///
+/// let _tmr1 = timer_id!(PARSE);
+/// let _tmr2 = timer_id!(Level::Info; PARSE, "Parsing {} bytes", 42);
+/// ```
+#[macro_export]
+macro_rules! timer_id {
+    ($id:expr) => {
+        $crate::timer!($crate::TimerName::Id($id))
+    };
+
+    ($level:expr; $id:expr) => {
+        $crate::timer!($level; $crate::TimerName::Id($id))
+    };
+
+    ($id:expr, $($arg:tt)*) => {
+        $crate::timer!($crate::TimerName::Id($id), $($arg)*)
+    };
+
+    ($level:expr; $id:expr, $($arg:tt)*) => {
+        $crate::timer!($level; $crate::TimerName::Id($id), $($arg)*)
+    };
+}
 
 /// Makes an existing timer output an 'executing' mesasge.
 /// Can be called multiple times.
@@ -582,6 +2020,566 @@ macro_rules! executing {
     })
 }
 
+/// Makes an existing timer, created when an operation was queued, record that the operation
+/// has started running. A 'queued' message giving the time spent in the queue is logged,
+/// and the 'finished' message will give the queue wait and the running time separately.
+/// The timer must be declared `mut`.
+///
+/// # Examples
+///
+/// ```norun
+/// use logging_timer::{started, timer};
+///
+/// let mut tmr = timer!("JOB", "Id={}", job.id);
+/// queue.push((job, tmr));
+///
+/// // Later, on a worker thread.
+/// let (job, mut tmr) = queue.pop();
+/// started!(tmr);      // Logs "JOB, Elapsed=12ms, Id=42" with a target of TimerQueued
+/// job.run();
+/// // Logs "JOB, Elapsed=42ms, QueueWait=12ms, RunElapsed=30ms, Id=42"
+/// ```
+#[macro_export]
+macro_rules! started {
+    ($timer:expr) => {
+        if let Some(ref mut tmr) = $timer {
+            tmr.started();
+        }
+    };
+}
+
+/// Starts a named phase within an existing timer, returning an optional `PhaseGuard`.
+/// The timer outputs an 'executing' message when the phase starts, and another one
+/// including the elapsed time of the phase when the guard is dropped.
+///
+/// # Examples
+///
+/// ```norun
+///
+/// use logging_timer::{phase, timer};
+///
+/// let tmr = timer!("LOAD_CONFIG");
+/// {
+///     let _p = phase!(tmr, "PARSE");
+///     // parsing here
+/// }
+/// {
+///     let _p = phase!(tmr, "VALIDATE");
+///     // validation here
+/// }
+/// ```
+#[macro_export]
+macro_rules! phase {
+    ($timer:expr, $name:expr) => {
+        match $timer {
+            Some(ref tmr) => Some(tmr.phase($name)),
+            None => None,
+        }
+    };
+}
+
+/// Silences all timers on the current thread while the block is executing, then
+/// restores them. The value of the block is returned. This is useful for suppressing
+/// timing noise during warmup or initialization without changing the log levels.
+///
+/// # Examples
+///
+/// ```norun
+///
+/// use logging_timer::silence_timers;
+///
+/// let config = silence_timers!({
+///     load_config()   // Any timers in here log nothing
+/// });
+/// ```
+#[macro_export]
+macro_rules! silence_timers {
+    ($body:block) => {{
+        let _silence = $crate::silence_timers();
+        $body
+    }};
+}
+
+/// Logs the messages of all the timers created on the current thread while the block is
+/// executing with the given log target, instead of `TimerStarting`, `TimerFinished` and
+/// so on. The value of the block is returned. Scopes can be nested, the innermost target
+/// winning. This lets middleware route all the timing of a request to a dedicated target
+/// without changing the code being timed.
+///
+/// # Examples
+///
+/// ```norun
+/// use logging_timer::with_timer_target;
+///
+/// let response = with_timer_target!("request.timing", {
+///     handle(request)     // Timers in here log with a target of "request.timing"
+/// });
+/// ```
+#[macro_export]
+macro_rules! with_timer_target {
+    ($target:expr, $body:block) => {{
+        let _target = $crate::set_timer_target($target);
+        $body
+    }};
+}
+
+/// Times two alternative implementations of the same thing and logs which was faster,
+/// and by how much, in a single 'finished' message such as
+///
+/// `CONCAT, Elapsed=3.1ms, A=2.2ms, B=0.9ms, Faster=B, Difference=1.3ms, Ratio=2.44`
+///
+/// The blocks are always run in order, first `a` and then `b`, on the current thread,
+/// so if they have side effects then `b` sees the effects of `a`. Both blocks are run
+/// even if the timer is disabled. The value of block `a` is returned, or that of
+/// block `b` if `keep = b` is given; the other value is dropped after both blocks have
+/// run. The level defaults to Debug, as with `timer!`.
+///
+/// # Examples
+///
+/// ```norun
+/// use logging_timer::ab_time;
+///
+/// let s = ab_time!("CONCAT", { parts.concat() }, { parts.join("") });
+/// let s = ab_time!(Level::Info; "CONCAT", { parts.concat() }, { parts.join("") }, keep = b);
+/// ```
+#[macro_export]
+macro_rules! ab_time {
+    ($name:expr, $a:block, $b:block) => {
+        $crate::ab_time!($crate::Level::Debug; $name, $a, $b, keep = a)
+    };
+
+    ($name:expr, $a:block, $b:block, keep = $keep:ident) => {
+        $crate::ab_time!($crate::Level::Debug; $name, $a, $b, keep = $keep)
+    };
+
+    ($level:expr; $name:expr, $a:block, $b:block) => {
+        $crate::ab_time!($level; $name, $a, $b, keep = a)
+    };
+
+    ($level:expr; $name:expr, $a:block, $b:block, keep = a) => {
+        $crate::__ab_time!($level; $name, $a, $b).0
+    };
+
+    ($level:expr; $name:expr, $a:block, $b:block, keep = b) => {
+        $crate::__ab_time!($level; $name, $a, $b).1
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ab_time {
+    ($level:expr; $name:expr, $a:block, $b:block) => {{
+        let tmr = $crate::timer!($level; $name);
+        let a = $a;
+        let elapsed_a = tmr.as_ref().map(|t| t.elapsed());
+        let b = $b;
+        if let (Some(tmr), Some(elapsed_a)) = (tmr, elapsed_a) {
+            $crate::__ab_finish(tmr, elapsed_a);
+        }
+        (a, b)
+    }};
+}
+
+/// Aggregates all the timers with a given name that finish on the current thread until
+/// the end of the enclosing scope, then logs a single `TimerStats` message giving their
+/// count, total, mean, min and max elapsed times. This is ideal for summarising the
+/// timers inside a loop when the loop ends, without enabling the global statistics.
+///
+/// # Examples
+///
+/// ```norun
+/// use logging_timer::{aggregate_scope, timer};
+///
+/// {
+///     let _agg = aggregate_scope!("PARSE");
+///     for file in files {
+///         let _tmr = timer!(Level::Trace; "PARSE");
+///         parse(file);
+///     }
+/// }   // Logs "PARSE, Count=120, Total=1.2s, Mean=10ms, Min=2ms, Max=85ms"
+/// ```
+#[macro_export]
+macro_rules! aggregate_scope {
+    ($name:expr) => {
+        $crate::aggregate_scope($name)
+    };
+}
+
+/// Calls a closure returning a `Result` until it succeeds, making at most `max` attempts,
+/// and returns the result of the last attempt. One timer covers all the attempts: each
+/// attempt is logged as an 'executing' message stating the attempt number, how long it
+/// took and whether it succeeded, and the 'finished' message gives the total elapsed time
+/// and the number of attempts used. The closure is always called at least once, even if
+/// the timer is disabled. The level defaults to Debug, as with `timer!`.
+///
+/// ```text
+/// TimerExecuting FETCH, Elapsed=1.2s, Attempt=1, AttemptElapsed=1.2s, Result=Err
+/// TimerExecuting FETCH, Elapsed=1.5s, Attempt=2, AttemptElapsed=0.3s, Result=Ok
+/// TimerFinished  FETCH, Elapsed=1.5s, Attempts=2, Result=Ok
+/// ```
+///
+/// # Examples
+///
+/// ```norun
+/// use logging_timer::retry_timed;
+///
+/// let body = retry_timed!("FETCH", max = 3, || client.get(url))?;
+/// let body = retry_timed!(Level::Info; "FETCH", max = 3, || client.get(url))?;
+/// ```
+#[macro_export]
+macro_rules! retry_timed {
+    ($name:expr, max = $max:expr, $op:expr) => {
+        $crate::__retry_timed($crate::timer!($name), $max, $op)
+    };
+
+    ($level:expr; $name:expr, max = $max:expr, $op:expr) => {
+        $crate::__retry_timed($crate::timer!($level; $name), $max, $op)
+    };
+}
+
+/// The same as `retry_timed!`, except that it waits before each retry, for `base` before
+/// the second attempt and twice as long as the previous wait before each one after that.
+/// Each attempt's 'executing' message also states how long was waited before it, and the
+/// 'finished' message the total time spent waiting, so it is easy to see when the backoff
+/// rather than the operation dominates the latency, as in a retry storm. The waits are
+/// made with `std::thread::sleep`, so this is not for use in async code.
+///
+/// ```text
+/// TimerExecuting FETCH, Elapsed=1.2s, Attempt=1, Waited=0ns, AttemptElapsed=1.2s, Result=Err
+/// TimerExecuting FETCH, Elapsed=1.6s, Attempt=2, Waited=100ms, AttemptElapsed=0.3s, Result=Err
+/// TimerExecuting FETCH, Elapsed=2.0s, Attempt=3, Waited=200ms, AttemptElapsed=0.2s, Result=Ok
+/// TimerFinished  FETCH, Elapsed=2.0s, Attempts=3, Waited=300ms, Result=Ok
+/// ```
+///
+/// # Examples
+///
+/// ```norun
+/// use logging_timer::retry_backoff_timed;
+///
+/// let base = Duration::from_millis(100);
+/// let body = retry_backoff_timed!("FETCH", base = base, max = 5, || client.get(url))?;
+/// let body = retry_backoff_timed!(Level::Info; "FETCH", base = base, max = 5, || client.get(url))?;
+/// ```
+#[macro_export]
+macro_rules! retry_backoff_timed {
+    ($name:expr, base = $base:expr, max = $max:expr, $op:expr) => {
+        $crate::__retry_backoff_timed($crate::timer!($name), $base, $max, $op)
+    };
+
+    ($level:expr; $name:expr, base = $base:expr, max = $max:expr, $op:expr) => {
+        $crate::__retry_backoff_timed($crate::timer!($level; $name), $base, $max, $op)
+    };
+}
+
+/// Times the rest of the current scope, with no name to invent and no variable to manage.
+/// The timer is named after the enclosing function, e.g. `find_files()`, and the line of
+/// the macro is logged as usual, so several uses in one function can be told apart. It is
+/// bound to a hidden variable which lives until the end of the scope, so it cannot be
+/// dropped immediately by mistake. The level defaults to Debug, as with `timer!`.
+///
+/// # Examples
+///
+/// ```norun
+/// use logging_timer::{time_here, Level};
+///
+/// fn find_files(dir: PathBuf) -> Vec<PathBuf> {
+///     time_here!();
+///     // ...
+/// } // Logs "find_files(), Elapsed=28.835275ms"
+///
+/// fn load() {
+///     let config = read_config();
+///     {
+///         time_here!(Level::Info);
+///         parse(config);
+///     } // Logs "load(), Elapsed=..." at Info
+/// }
+/// ```
+#[macro_export]
+macro_rules! time_here {
+    () => {
+        $crate::time_here!($crate::Level::Debug)
+    };
+
+    ($level:expr) => {
+        let _logging_timer_here = $crate::timer!(
+            $level;
+            $crate::__enclosing_fn_name({
+                fn __logging_timer_here() {}
+                __logging_timer_here
+            })
+        );
+    };
+}
+
+/// Times the evaluation of an expression and returns its value. The timer is named after
+/// the enclosing function and a label, e.g. `dispatch()[ADD]`, so it can be dropped into
+/// one branch of a hot piece of code without restructuring it. The level defaults to
+/// Trace, because such code is usually too hot to log every call; turn on
+/// `enable_timer_stats` to aggregate the calls by name instead, which counts them
+/// whatever their level.
+///
+/// # Examples
+///
+/// ```norun
+/// use logging_timer::{time_expr, Level};
+///
+/// fn dispatch(op: Op) -> i64 {
+///     match op {
+///         Op::Add(a, b) => time_expr!("ADD", a + b),
+///         Op::Div(a, b) => time_expr!(Level::Debug; "DIV", checked_div(a, b)),
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! time_expr {
+    ($label:expr, $body:expr) => {
+        $crate::time_expr!($crate::Level::Trace; $label, $body)
+    };
+
+    ($level:expr; $label:expr, $body:expr) => {{
+        let _logging_timer_expr = $crate::timer!(
+            $level;
+            format!(
+                "{}[{}]",
+                $crate::__enclosing_fn_name({
+                    fn __logging_timer_here() {}
+                    __logging_timer_here
+                }),
+                $label
+            )
+        );
+        // A block body, as allowed in a `timed_match!` arm, is not redundant here.
+        #[allow(unused_braces)]
+        let value = $body;
+        value
+    }};
+}
+
+/// A `match` which times each of its arms, as if each arm's body were wrapped in
+/// `time_expr!`, labelled with the arm's pattern. For example an arm `Op::Add(a, b)` in
+/// `dispatch` is timed as `dispatch()[Op::Add(a, b)]`. With `enable_timer_stats` turned
+/// on, `dump_timer_stats` then shows which arm dominates. The arms must be separated by
+/// commas, even when their bodies are blocks. The level defaults to Trace, as with
+/// `time_expr!`.
+///
+/// # Examples
+///
+/// ```norun
+/// use logging_timer::{timed_match, Level};
+///
+/// let result = timed_match!(op, {
+///     Op::Add(a, b) => a + b,
+///     Op::Neg(a) | Op::Not(a) => -a,
+///     Op::Div(a, b) if b != 0 => { a / b },
+///     _ => 0,
+/// });
+///
+/// let result = timed_match!(Level::Debug; op, {
+///     Op::Add(a, b) => a + b,
+///     _ => 0,
+/// });
+/// ```
+#[macro_export]
+macro_rules! timed_match {
+    ($level:expr; $scrutinee:expr, {
+        $( $($pat:pat)|+ $(if $guard:expr)? => $body:expr ),* $(,)?
+    }) => {
+        match $scrutinee {
+            $(
+                $($pat)|+ $(if $guard)? => $crate::time_expr!(
+                    $level;
+                    stringify!($($pat)|+),
+                    $body
+                ),
+            )*
+        }
+    };
+
+    ($scrutinee:expr, {
+        $( $($pat:pat)|+ $(if $guard:expr)? => $body:expr ),* $(,)?
+    }) => {
+        $crate::timed_match!($crate::Level::Trace; $scrutinee, {
+            $( $($pat)|+ $(if $guard)? => $body ),*
+        })
+    };
+}
+
+/// Times a function body that has several `return` statements, so that the timer finishes
+/// once, with one message, whichever `return` is taken. This is for code that cannot use
+/// the `#[time]` attribute. The body is run as a closure, so `return` and `?` leave the
+/// body rather than the function, and the value of the body is returned; it cannot use
+/// `.await`, `break` or `continue` for loops outside it.
+///
+/// A `finish` closure can be given, which makes the extra information of the 'finished'
+/// message from the value being returned, whichever `return` produced it. The level defaults
+/// to Debug, as with `timer!`.
+///
+/// # Examples
+///
+/// ```norun
+/// use logging_timer::{time_fn, Level};
+///
+/// fn lookup(key: &str) -> Option<Entry> {
+///     time_fn!("LOOKUP", {
+///         if key.is_empty() {
+///             return None;
+///         }
+///         let entry = cache.get(key)?;
+///         Some(entry.clone())
+///     })
+/// }
+///
+/// fn load(path: &Path) -> Result<Vec<Row>, Error> {
+///     time_fn!(Level::Info; "LOAD", finish = |r| match r {
+///         Ok(rows) => format!("Rows={}", rows.len()),
+///         Err(e) => format!("Error={}", e),
+///     }, {
+///         if !path.exists() {
+///             return Ok(Vec::new());
+///         }
+///         let rows = parse(std::fs::read(path)?)?;
+///         Ok(rows)
+///     })  // Logs "LOAD, Elapsed=12ms, Rows=3" whichever return was taken
+/// }
+/// ```
+#[macro_export]
+macro_rules! time_fn {
+    ($level:expr; $name:expr, finish = $finish:expr, $body:block) => {
+        $crate::__time_fn_finish($crate::timer!($level; $name), || $body, $finish)
+    };
+
+    ($level:expr; $name:expr, $body:block) => {
+        $crate::__time_fn($crate::timer!($level; $name), || $body)
+    };
+
+    ($name:expr, finish = $finish:expr, $body:block) => {
+        $crate::time_fn!($crate::Level::Debug; $name, finish = $finish, $body)
+    };
+
+    ($name:expr, $body:block) => {
+        $crate::time_fn!($crate::Level::Debug; $name, $body)
+    };
+}
+
+/// Gets the value of a `OnceLock`, or any other type with a `get_or_init` method such as
+/// `OnceCell`, timing the initializer if this is the call that runs it. The timer only
+/// exists while the value is actually being initialized, so later, cached, accesses log
+/// nothing and cost nothing extra. This answers the common question of how long each
+/// piece of startup initialization took. The level defaults to Debug, as with `timer!`.
+///
+/// # Examples
+///
+/// ```norun
+/// use logging_timer::{time_init, Level};
+///
+/// static CONFIG: OnceLock<Config> = OnceLock::new();
+///
+/// fn config() -> &'static Config {
+///     time_init!("LOAD_CONFIG", CONFIG, || Config::load())
+/// }
+///
+/// let index = time_init!(Level::Info; "BUILD_INDEX", self.index, || build_index(&self.docs));
+/// ```
+#[macro_export]
+macro_rules! time_init {
+    ($level:expr; $name:expr, $once:expr, $init:expr) => {
+        $once.get_or_init(|| {
+            let _tmr = $crate::timer!($level; $name);
+            ($init)()
+        })
+    };
+
+    ($name:expr, $once:expr, $init:expr) => {
+        $crate::time_init!($crate::Level::Debug; $name, $once, $init)
+    };
+}
+
+/// Acquires a lock, timing how long it takes to acquire it, and returns the result of
+/// the acquisition. The timer finishes as soon as the lock is acquired, so the elapsed
+/// time is the time spent waiting for the lock and not the time for which it is held.
+/// This is useful for diagnosing lock contention.
+///
+/// By default the lock is acquired by calling its `lock` method, which suits a `Mutex`.
+/// For a `RwLock`, name the method to call as the third argument. The level defaults
+/// to Debug, as with `timer!`.
+///
+/// # Examples
+///
+/// ```norun
+/// use logging_timer::timed_lock;
+///
+/// let guard = timed_lock!("CACHE_LOCK", cache_mutex).unwrap();
+/// let guard = timed_lock!("INDEX_READ", index_rwlock, read).unwrap();
+/// let guard = timed_lock!(Level::Info; "INDEX_WRITE", index_rwlock, write).unwrap();
+/// ```
+#[macro_export]
+macro_rules! timed_lock {
+    ($name:expr, $lock:expr) => {
+        $crate::timed_lock!($crate::Level::Debug; $name, $lock, lock)
+    };
+
+    ($name:expr, $lock:expr, $method:ident) => {
+        $crate::timed_lock!($crate::Level::Debug; $name, $lock, $method)
+    };
+
+    ($level:expr; $name:expr, $lock:expr) => {
+        $crate::timed_lock!($level; $name, $lock, lock)
+    };
+
+    ($level:expr; $name:expr, $lock:expr, $method:ident) => {{
+        let tmr = $crate::timer!($level; $name);
+        let result = $lock.$method();
+        $crate::finish!(tmr);
+        result
+    }};
+}
+
+/// Asserts that a timer with the given name has logged a 'finished' message on the current
+/// thread, for testing that your `#[time]` attributes and `timer!` calls are present and
+/// firing. Install the test logger with `init_test_logger()` first. Requires the
+/// `test-util` feature, typically enabled only in your dev-dependencies.
+///
+/// # Examples
+///
+/// ```norun
+/// #[test]
+/// fn load_is_timed() {
+///     logging_timer::init_test_logger();
+///     load_config();
+///     logging_timer::assert_timer_logged!("load_config()");
+/// }
+/// ```
+#[cfg(feature = "test-util")]
+#[macro_export]
+macro_rules! assert_timer_logged {
+    ($name:expr) => {
+        $crate::__assert_timer_logged($name)
+    };
+}
+
+/// Makes an existing timer output a 'finished' message stating the number of things
+/// processed and the rate at which they were processed, and suppresses the normal drop
+/// message. The count can be any integer.
+///
+/// # Examples
+///
+/// ```norun
+/// use logging_timer::{finish_rate, timer};
+///
+/// let tmr = timer!("IMPORT");
+/// let rows = import(file);
+/// finish_rate!(tmr, rows.len(), "rows"); // Logs "IMPORT, Elapsed=0.5s, Processed 1500 rows (3000.00/s)"
+/// ```
+#[macro_export]
+macro_rules! finish_rate {
+    ($timer:expr, $count:expr, $unit:expr) => {{
+        if let Some(ref tmr) = $timer {
+            tmr.finish_rate($count as u64, $unit)
+        }
+    }};
+}
+
 /// Makes an existing timer output a 'finished' mesasge and suppresses
 /// the normal drop message.
 /// Only the first call has any effect, subsequent calls will be ignored.
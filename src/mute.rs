@@ -0,0 +1,62 @@
+use crate::TimerName;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{OnceLock, RwLock};
+
+/// The environment variable holding a comma-separated list of timer names to mute.
+const MUTE_VAR: &str = "LOGGING_TIMER_MUTE";
+
+// Set whenever any name is muted, so that the usual case needs no lock.
+static ANY_MUTED: AtomicBool = AtomicBool::new(false);
+
+fn muted() -> &'static RwLock<HashSet<String>> {
+    static MUTED: OnceLock<RwLock<HashSet<String>>> = OnceLock::new();
+    MUTED.get_or_init(|| {
+        let names: HashSet<String> = std::env::var(MUTE_VAR)
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect();
+        ANY_MUTED.store(!names.is_empty(), Ordering::Relaxed);
+        RwLock::new(names)
+    })
+}
+
+/// Mutes the timers called `name`: timers with that name are not created, so they log
+/// nothing and are not counted in the statistics. This is for silencing one chatty timer,
+/// during an incident for example, without touching any others. Timers named by id are
+/// muted by the name registered for the id.
+///
+/// Names can also be muted without recompiling by listing them, separated by commas, in
+/// the `LOGGING_TIMER_MUTE` environment variable, e.g. `LOGGING_TIMER_MUTE=PARSE,WRITE`.
+/// The variable is read when the first timer is created.
+pub fn mute_timer(name: impl Into<String>) {
+    let mut muted = muted().write().unwrap_or_else(|e| e.into_inner());
+    muted.insert(name.into());
+    ANY_MUTED.store(true, Ordering::Relaxed);
+}
+
+/// Unmutes the timers called `name`, which were muted by `mute_timer` or the
+/// `LOGGING_TIMER_MUTE` environment variable.
+pub fn unmute_timer(name: &str) {
+    let mut muted = muted().write().unwrap_or_else(|e| e.into_inner());
+    muted.remove(name);
+    ANY_MUTED.store(!muted.is_empty(), Ordering::Relaxed);
+}
+
+/// Returns true if timers called `name` are muted.
+pub(crate) fn is_muted(name: &TimerName) -> bool {
+    let muted = muted();
+    if !ANY_MUTED.load(Ordering::Relaxed) {
+        return false;
+    }
+
+    let muted = muted.read().unwrap_or_else(|e| e.into_inner());
+    match name {
+        TimerName::Str(name) => muted.contains(*name),
+        TimerName::Owned(name) => muted.contains(name.as_str()),
+        TimerName::Id(_) => muted.contains(&name.to_string()),
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{OnceLock, RwLock};
+
+/// The name of a timer.
+///
+/// Normally this is just a string, which is what you get when you write `timer!("FIND_FILES")`.
+/// A timer can also own its name, which is what you get when you pass a `String`; since such
+/// a timer does not borrow anything it is a `LoggingTimer<'static>` and can be stored or
+/// passed around freely.
+///
+/// In extremely hot code you can use a numeric id instead (see the `timer_id!` macro), which
+/// is cheaper to carry around and compare. The id is only turned into a name, via the table
+/// maintained by `register_timer_name`, at the moment a message is actually logged.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TimerName<'name> {
+    /// A timer named by a string.
+    Str(&'name str),
+    /// A timer which owns its name.
+    Owned(String),
+    /// A timer named by a numeric id.
+    Id(u32),
+}
+
+impl<'name> From<&'name str> for TimerName<'name> {
+    fn from(name: &'name str) -> Self {
+        TimerName::Str(name)
+    }
+}
+
+impl<'name> From<String> for TimerName<'name> {
+    fn from(name: String) -> Self {
+        TimerName::Owned(name)
+    }
+}
+
+impl<'name> fmt::Display for TimerName<'name> {
+    /// Writes the name of the timer. Ids are looked up in the table of registered names,
+    /// if an id has not been registered it is written as `#<id>`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TimerName::Str(name) => f.write_str(name),
+            TimerName::Owned(name) => f.write_str(name),
+            TimerName::Id(id) => match registered_name(*id) {
+                Some(name) => f.write_str(name),
+                None => write!(f, "#{}", id),
+            },
+        }
+    }
+}
+
+fn id_table() -> &'static RwLock<HashMap<u32, &'static str>> {
+    static TABLE: OnceLock<RwLock<HashMap<u32, &'static str>>> = OnceLock::new();
+    TABLE.get_or_init(Default::default)
+}
+
+/// Registers the name to be used when logging timers created with `timer_id!(id)`.
+/// Typically you do this once at startup for all the ids you use. Registering an id
+/// a second time replaces the previous name.
+pub fn register_timer_name(id: u32, name: &'static str) {
+    id_table().write().unwrap_or_else(|e| e.into_inner()).insert(id, name);
+}
+
+/// Registers several id to name mappings in one go. See `register_timer_name`.
+pub fn register_timer_names(names: &[(u32, &'static str)]) {
+    let mut table = id_table().write().unwrap_or_else(|e| e.into_inner());
+    table.extend(names.iter().copied());
+}
+
+fn registered_name(id: u32) -> Option<&'static str> {
+    id_table().read().unwrap_or_else(|e| e.into_inner()).get(&id).copied()
+}
+
+/// Implementation of `time_here!`. Returns the name of the function in which `time_here!`
+/// is used, followed by `()`, worked out from the type name of an item that the macro
+/// defines inside it. Closures and async blocks are skipped over.
+#[doc(hidden)]
+pub fn __enclosing_fn_name<T>(_item: T) -> String {
+    let path = std::any::type_name::<T>();
+    let mut path = path.strip_suffix("::__logging_timer_here").unwrap_or(path);
+    while let Some(outer) = path.strip_suffix("::{{closure}}") {
+        path = outer;
+    }
+    let name = path.rsplit("::").next().unwrap_or(path);
+    format!("{}()", name)
+}
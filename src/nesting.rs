@@ -0,0 +1,161 @@
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::thread::ThreadId;
+use std::time::{Duration, Instant};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static SPANS: AtomicBool = AtomicBool::new(false);
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+// Timers which finished on a different thread from the one that started them, with that
+// thread. Only the starting thread can reach its own list of running timers, so it removes
+// them from it the next time it starts or finishes a timer.
+static ORPHANS: Mutex<Vec<(ThreadId, u64)>> = Mutex::new(Vec::new());
+static HAS_ORPHANS: AtomicBool = AtomicBool::new(false);
+
+fn orphans() -> MutexGuard<'static, Vec<(ThreadId, u64)>> {
+    ORPHANS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A timer which is running on the current thread.
+pub(crate) struct ActiveTimer {
+    pub(crate) id: u64,
+    pub(crate) name: String,
+    pub(crate) start_time: Instant,
+    /// The total elapsed time of the timers which have finished while nested directly
+    /// inside this one.
+    pub(crate) child_time: Duration,
+}
+
+thread_local! {
+    // The timers running on this thread, outermost first.
+    static ACTIVE: RefCell<Vec<ActiveTimer>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Turns on the tracking of which timers are running on each thread, and so how they
+/// are nested. This is needed by `LoggingTimer::with_parent`. It costs an allocation
+/// for each timer, so the default is off.
+pub fn enable_timer_nesting(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Makes every timer's messages include a `SpanId=` field, a number which identifies the
+/// timer uniquely within the process, and a `ParentSpanId=` field giving the id of the
+/// innermost timer that was running on the same thread when it started, if there was one.
+/// With these a log processor can rebuild the tree of timers offline, like a trace. This
+/// turns on the tracking of the nesting of timers, see `enable_timer_nesting`. The default
+/// is off.
+pub fn enable_timer_spans(enabled: bool) {
+    SPANS.store(enabled, Ordering::Relaxed);
+}
+
+#[inline]
+pub(crate) fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed) || spans_enabled() || flame_active()
+}
+
+#[inline]
+fn flame_active() -> bool {
+    #[cfg(feature = "flame-graph")]
+    return crate::flame::is_active();
+    #[cfg(not(feature = "flame-graph"))]
+    return false;
+}
+
+#[inline]
+pub(crate) fn spans_enabled() -> bool {
+    SPANS.load(Ordering::Relaxed)
+}
+
+/// Records that a timer has started on the current thread, returning its id and the
+/// id of the innermost timer already running on the thread, if any.
+pub(crate) fn push(name: String, start_time: Instant) -> (u64, Option<u64>) {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    ACTIVE.with(|a| {
+        let mut active = a.borrow_mut();
+        remove_orphans(&mut active);
+        let parent_id = active.last().map(|t| t.id);
+        active.push(ActiveTimer { id, name, start_time, child_time: Duration::ZERO });
+        (id, parent_id)
+    })
+}
+
+/// Records that a timer which started on `thread` has finished. Timers normally finish in
+/// the reverse order that they started, but not always, so the timer is searched for from
+/// the innermost. A timer finished on a different thread is left for its own thread to
+/// remove, and is not written to a flame graph. The `elapsed` time of the timer is added
+/// to the child time of the timer it was nested inside, so that the time each timer spent
+/// on its own can be written to a flame graph.
+pub(crate) fn remove(id: u64, thread: ThreadId, elapsed: Duration) {
+    if thread != std::thread::current().id() {
+        orphans().push((thread, id));
+        HAS_ORPHANS.store(true, Ordering::Relaxed);
+        return;
+    }
+
+    ACTIVE.with(|a| {
+        let mut active = a.borrow_mut();
+        remove_orphans(&mut active);
+        if let Some(index) = active.iter().rposition(|t| t.id == id) {
+            let timer = active.remove(index);
+            if let Some(parent) = index.checked_sub(1).map(|i| &mut active[i]) {
+                parent.child_time += elapsed;
+            }
+            #[cfg(feature = "flame-graph")]
+            if crate::flame::is_active() {
+                crate::flame::write_stack(
+                    active[..index].iter().map(|t| t.name.as_str()),
+                    &timer.name,
+                    elapsed.saturating_sub(timer.child_time),
+                );
+            }
+            #[cfg(not(feature = "flame-graph"))]
+            let _ = timer;
+        }
+    });
+}
+
+/// Records that a running timer, which started on `thread`, has been renamed. A timer
+/// which has moved to a different thread keeps its old name in the tracker.
+pub(crate) fn rename(id: u64, thread: ThreadId, name: String) {
+    if thread != std::thread::current().id() {
+        return;
+    }
+
+    ACTIVE.with(|a| {
+        if let Some(timer) = a.borrow_mut().iter_mut().rev().find(|t| t.id == id) {
+            timer.name = name;
+        }
+    });
+}
+
+/// Returns the start time of the innermost running timer called `name`, other than the
+/// timer with id `except`.
+pub(crate) fn find_start_time(name: &str, except: Option<u64>) -> Option<Instant> {
+    ACTIVE.with(|a| {
+        a.borrow()
+            .iter()
+            .rev()
+            .find(|t| t.name == name && Some(t.id) != except)
+            .map(|t| t.start_time)
+    })
+}
+
+// Removes the timers of this thread which have finished on other threads.
+fn remove_orphans(active: &mut Vec<ActiveTimer>) {
+    if !HAS_ORPHANS.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let thread = std::thread::current().id();
+    let mut orphans = orphans();
+    orphans.retain(|&(orphan_thread, id)| {
+        if orphan_thread != thread {
+            return true;
+        }
+        active.retain(|t| t.id != id);
+        false
+    });
+    HAS_ORPHANS.store(!orphans.is_empty(), Ordering::Relaxed);
+}
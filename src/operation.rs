@@ -0,0 +1,72 @@
+use std::cell::RefCell;
+use std::sync::Arc;
+
+thread_local! {
+    static OPERATION_ID: RefCell<Option<Arc<str>>> = const { RefCell::new(None) };
+}
+
+#[cfg(feature = "tokio")]
+tokio::task_local! {
+    static TASK_OPERATION_ID: Arc<str>;
+}
+
+/// While a value of this type is alive, timers created on the current thread include
+/// an `OperationId=` field in their messages, for correlating all the timers of one
+/// request or job. Create one with `set_operation_id`. When the guard is dropped the
+/// previous operation id, if any, is restored.
+pub struct OperationIdGuard {
+    previous: Option<Arc<str>>,
+    // Thread-local state, so the guard must not be sent to another thread.
+    _not_send: std::marker::PhantomData<*const ()>,
+}
+
+/// Sets the operation id of the timers created on the current thread until the returned
+/// guard is dropped. In async code, where a task can move between threads at each
+/// `.await`, use `with_operation_id` instead.
+pub fn set_operation_id(id: impl Into<String>) -> OperationIdGuard {
+    let id: Arc<str> = Arc::from(id.into());
+    let previous = OPERATION_ID.with(|current| current.borrow_mut().replace(id));
+    OperationIdGuard { previous, _not_send: std::marker::PhantomData }
+}
+
+impl Drop for OperationIdGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        OPERATION_ID.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// Runs `future` with an operation id that is included as an `OperationId=` field in the
+/// messages of every timer created within it, on whichever thread the future happens to
+/// be polled. The id is held in a tokio task-local, so it follows the future across
+/// `.await` points, which a thread-local cannot do. It takes precedence over any id set
+/// by `set_operation_id`. Requires the `tokio` feature.
+///
+/// # Examples
+///
+/// ```norun
+/// async fn handle(request: Request) -> Response {
+///     logging_timer::with_operation_id(request.id.to_string(), async {
+///         let _tmr = timer!("HANDLE");
+///         // Timers in here, and in anything awaited here, log OperationId=<request id>
+///     })
+///     .await
+/// }
+/// ```
+#[cfg(feature = "tokio")]
+pub async fn with_operation_id<F>(id: impl Into<String>, future: F) -> F::Output
+where
+    F: std::future::Future,
+{
+    TASK_OPERATION_ID.scope(Arc::from(id.into()), future).await
+}
+
+/// Returns the operation id in effect for a timer being created now.
+pub(crate) fn current() -> Option<Arc<str>> {
+    #[cfg(feature = "tokio")]
+    if let Ok(id) = TASK_OPERATION_ID.try_with(Arc::clone) {
+        return Some(id);
+    }
+
+    OPERATION_ID.with(|current| current.borrow().clone())
+}
@@ -0,0 +1,47 @@
+use crate::TimerTarget;
+use std::any::Any;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// An owned copy of a single message from a timer, for consumers that want to process
+/// timer events themselves rather than, or as well as, reading them from the log.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct TimerRecord {
+    /// Which of the timer's messages this is.
+    pub target: TimerTarget,
+    /// The level the message was produced at.
+    pub level: ::log::Level,
+    /// The name of the timer.
+    pub name: String,
+    /// The elapsed time of the timer when the message was produced. This is `None`
+    /// for the 'starting' message.
+    pub elapsed: Option<Duration>,
+    /// The complete text of the message, exactly as it is logged.
+    pub message: String,
+    /// The file in which the timer was created.
+    pub file: &'static str,
+    /// The module in which the timer was created, if it is known.
+    pub module_path: Option<&'static str>,
+    /// The line on which the timer was created.
+    pub line: u32,
+    /// The context attached to the timer by `LoggingTimer::with_context`.
+    pub(crate) context: Option<Arc<dyn Any + Send + Sync>>,
+}
+
+impl TimerRecord {
+    /// Returns the context attached to the timer by `LoggingTimer::with_context`, if
+    /// there is one and it is of type `C`. This lets a consumer of the records render
+    /// fields of its own from structured data that the standard message ignores.
+    ///
+    /// ```norun
+    /// for record in logging_timer::recent_timers() {
+    ///     if let Some(request) = record.context::<RequestInfo>() {
+    ///         println!("{} {} {}", record.name, request.route, request.user_id);
+    ///     }
+    /// }
+    /// ```
+    pub fn context<C: Any>(&self) -> Option<&C> {
+        self.context.as_deref()?.downcast_ref()
+    }
+}
@@ -0,0 +1,141 @@
+use crate::{TimerName, TimerTarget, format};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// A timer message captured by `start_timer_recording`, holding the parts the message was
+/// built from rather than its text, so that it can be rendered again by `replay_timers`.
+/// With the `serde` feature it can be serialized, to save a recording for use in tests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct RecordedTimer {
+    /// Which of the timer's messages this is.
+    pub target: TimerTarget,
+    /// The level the message was produced at.
+    pub level: ::log::Level,
+    /// The name of the timer.
+    pub name: String,
+    /// When the timer started, relative to the start of the recording. Timers which
+    /// started before the recording have an offset of zero.
+    pub start_offset: Duration,
+    /// The elapsed time in the message, if it had one.
+    pub elapsed: Option<Duration>,
+    /// The extra information given when the timer was created.
+    pub extra_info: Option<String>,
+    /// The formatted arguments passed to the message, such as those of `executing!`.
+    pub args: Option<String>,
+    /// The file in which the timer was created.
+    pub file: String,
+    /// The module in which the timer was created, if it is known.
+    pub module_path: Option<String>,
+    /// The line on which the timer was created.
+    pub line: u32,
+}
+
+struct Recording {
+    start: Instant,
+    timers: Vec<RecordedTimer>,
+}
+
+static RECORDING: Mutex<Option<Recording>> = Mutex::new(None);
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+fn recording() -> MutexGuard<'static, Option<Recording>> {
+    RECORDING.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Starts capturing every timer message, regardless of its log level, until
+/// `finish_timer_recording` is called. Messages continue to be logged as normal.
+/// Starting a recording discards any recording already in progress.
+pub fn start_timer_recording() {
+    *recording() = Some(Recording { start: crate::clock::now(), timers: Vec::new() });
+    ACTIVE.store(true, Ordering::Relaxed);
+}
+
+/// Stops capturing timer messages and returns those captured since `start_timer_recording`,
+/// in the order they were produced.
+pub fn finish_timer_recording() -> Vec<RecordedTimer> {
+    ACTIVE.store(false, Ordering::Relaxed);
+    recording().take().map(|r| r.timers).unwrap_or_default()
+}
+
+/// Logs the recorded timer messages again, in order, each at its original level and with
+/// its original elapsed time. The messages are formatted with the current settings, such
+/// as the message format, duration format and field separator, so a fixed recording can
+/// be used to check the output of those settings, or of a change to the formatting code.
+/// Only the name, elapsed time, extra information and arguments of each message are
+/// recorded, so fields such as the thread label and span ids are not reproduced.
+///
+/// # Examples
+///
+/// ```norun
+/// logging_timer::start_timer_recording();
+/// run_workload();
+/// let recording = logging_timer::finish_timer_recording();
+/// std::fs::write("timers.json", serde_json::to_string(&recording)?)?;
+///
+/// // Later, in a test.
+/// let recording: Vec<RecordedTimer> = serde_json::from_str(&std::fs::read_to_string("timers.json")?)?;
+/// logging_timer::replay_timers(&recording);
+/// ```
+pub fn replay_timers(timers: &[RecordedTimer]) {
+    for timer in timers {
+        match &timer.args {
+            Some(args) => replay(timer, Some(format_args!("{}", args))),
+            None => replay(timer, None),
+        }
+    }
+}
+
+fn replay(timer: &RecordedTimer, args: Option<fmt::Arguments>) {
+    let name = TimerName::Str(&timer.name);
+    let message = format::Message {
+        target: timer.target,
+        file: &timer.file,
+        line: timer.line,
+        name: &name,
+        operation_id: None,
+        thread_label: None,
+        tracing_span: None,
+        span: None,
+        elapsed: timer.elapsed,
+        request_elapsed: None,
+        duration_format: None,
+        parent_fraction: None,
+        wall_clock: None,
+        cpu: None,
+        queue: None,
+        phases: None,
+        extra_info: timer.extra_info.as_deref(),
+        args,
+    };
+    crate::emit(
+        &::log::RecordBuilder::new()
+            .level(timer.level)
+            .target(format::target(timer.target))
+            .file(Some(&timer.file))
+            .module_path(timer.module_path.as_deref())
+            .line(Some(timer.line))
+            .args(format_args!("{}", message))
+            .build(),
+    );
+}
+
+/// Returns true if timer messages are being recorded.
+#[inline]
+pub(crate) fn is_active() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+/// Returns the offset of `start_time` from the start of the recording.
+pub(crate) fn start_offset(start_time: Instant) -> Duration {
+    recording().as_ref().map_or(Duration::ZERO, |r| start_time.saturating_duration_since(r.start))
+}
+
+pub(crate) fn push(timer: RecordedTimer) {
+    if let Some(recording) = recording().as_mut() {
+        recording.timers.push(timer);
+    }
+}
@@ -0,0 +1,52 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+
+type RequestStartSource = Arc<dyn Fn() -> Option<Instant> + Send + Sync>;
+
+static SOURCE: RwLock<Option<RequestStartSource>> = RwLock::new(None);
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Installs a function which returns the start time of the request (or job, or whatever
+/// larger unit of work) that is in progress, typically read from a thread-local or a
+/// task-local that you set when the request arrives. It is called when each timer is
+/// created, and if it returns a start time the timer's messages include a
+/// `RequestElapsed=` field, the time since the request started, alongside the timer's
+/// own elapsed time. This shows both how long an operation took and when it happened
+/// within the overall request. Installing a source replaces any previous one.
+///
+/// The start time should be taken from the same clock as the timers, which is
+/// `Instant::now()` unless a `Clock` has been installed.
+///
+/// # Examples
+///
+/// ```norun
+/// thread_local! {
+///     static REQUEST_START: Cell<Option<Instant>> = Cell::new(None);
+/// }
+///
+/// logging_timer::set_request_start_source(|| REQUEST_START.with(Cell::get));
+/// ```
+pub fn set_request_start_source<F>(source: F)
+where
+    F: Fn() -> Option<Instant> + Send + Sync + 'static,
+{
+    *SOURCE.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(source));
+    ACTIVE.store(true, Ordering::Relaxed);
+}
+
+/// Removes the source installed by `set_request_start_source`.
+pub fn clear_request_start_source() {
+    ACTIVE.store(false, Ordering::Relaxed);
+    *SOURCE.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Returns the start time of the current request, if there is a source and it knows one.
+pub(crate) fn current() -> Option<Instant> {
+    if !ACTIVE.load(Ordering::Relaxed) {
+        return None;
+    }
+
+    let source = SOURCE.read().unwrap_or_else(|e| e.into_inner()).clone();
+    source.and_then(|source| source())
+}
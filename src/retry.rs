@@ -0,0 +1,103 @@
+use crate::LoggingTimer;
+use crate::format::{FormattedDuration, separator};
+use std::time::Duration;
+
+/// Implementation of `retry_timed!`. Calls `op` until it succeeds or `max` attempts
+/// have been made, logging each attempt as an 'executing' message of the timer (if
+/// there is one) and finishing the timer with the number of attempts used.
+#[doc(hidden)]
+pub fn __retry_timed<T, E, F>(tmr: Option<LoggingTimer>, max: u32, op: F) -> Result<T, E>
+where
+    F: FnMut() -> Result<T, E>,
+{
+    retry(tmr, max, None, op)
+}
+
+/// Implementation of `retry_backoff_timed!`. As `__retry_timed`, but sleeps before each
+/// retry, for `base_delay` before the second attempt and twice as long before each one
+/// after that, and includes the waits in the messages.
+#[doc(hidden)]
+pub fn __retry_backoff_timed<T, E, F>(
+    tmr: Option<LoggingTimer>,
+    base_delay: Duration,
+    max: u32,
+    op: F,
+) -> Result<T, E>
+where
+    F: FnMut() -> Result<T, E>,
+{
+    retry(tmr, max, Some(base_delay), op)
+}
+
+fn retry<T, E, F>(
+    tmr: Option<LoggingTimer>,
+    max: u32,
+    base_delay: Option<Duration>,
+    mut op: F,
+) -> Result<T, E>
+where
+    F: FnMut() -> Result<T, E>,
+{
+    let max = max.max(1);
+    let sep = separator();
+    let mut attempt = 0;
+    let mut delay = base_delay.unwrap_or_default();
+    let mut waited = Duration::ZERO;
+    let mut total_waited = Duration::ZERO;
+    loop {
+        attempt += 1;
+        let attempt_start = tmr.as_ref().map(|t| t.elapsed());
+        let result = op();
+
+        if let (Some(tmr), Some(attempt_start)) = (tmr.as_ref(), attempt_start) {
+            let outcome = if result.is_ok() { "Ok" } else { "Err" };
+            let attempt_elapsed = FormattedDuration(
+                tmr.elapsed().saturating_sub(attempt_start),
+                tmr.duration_format(),
+            );
+            if base_delay.is_some() {
+                tmr.executing(Some(format_args!(
+                    "Attempt={}{}Waited={}{}AttemptElapsed={}{}Result={}",
+                    attempt,
+                    sep,
+                    FormattedDuration(waited, tmr.duration_format()),
+                    sep,
+                    attempt_elapsed,
+                    sep,
+                    outcome
+                )));
+            } else {
+                tmr.executing(Some(format_args!(
+                    "Attempt={}{}AttemptElapsed={}{}Result={}",
+                    attempt, sep, attempt_elapsed, sep, outcome
+                )));
+            }
+        }
+
+        if result.is_ok() || attempt == max {
+            if let Some(tmr) = tmr.as_ref() {
+                let outcome = if result.is_ok() { "Ok" } else { "Err" };
+                if base_delay.is_some() {
+                    tmr.finish(Some(format_args!(
+                        "Attempts={}{}Waited={}{}Result={}",
+                        attempt,
+                        sep,
+                        FormattedDuration(total_waited, tmr.duration_format()),
+                        sep,
+                        outcome
+                    )));
+                } else {
+                    tmr.finish(Some(format_args!("Attempts={}{}Result={}", attempt, sep, outcome)));
+                }
+            }
+            return result;
+        }
+
+        if base_delay.is_some() {
+            std::thread::sleep(delay);
+            waited = delay;
+            total_waited = total_waited.saturating_add(delay);
+            delay = delay.saturating_mul(2);
+        }
+    }
+}
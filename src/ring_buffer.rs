@@ -0,0 +1,76 @@
+use crate::TimerRecord;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+static CAPACITY: AtomicUsize = AtomicUsize::new(0);
+static BUFFER: Mutex<VecDeque<TimerRecord>> = Mutex::new(VecDeque::new());
+
+fn buffer() -> MutexGuard<'static, VecDeque<TimerRecord>> {
+    BUFFER.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Keeps the most recent `capacity` timer messages in memory, regardless of the log level,
+/// so that they can be dumped after a failure with `dump_recent_timers`. This gives a
+/// history of what was slow just before a crash even when timers are not normally logged.
+/// A capacity of 0, the default, turns the buffer off. Timers are created regardless of
+/// their log level while the buffer is on.
+///
+/// # Examples
+///
+/// ```norun
+/// logging_timer::set_recent_timers_capacity(1000);
+///
+/// let default_hook = std::panic::take_hook();
+/// std::panic::set_hook(Box::new(move |info| {
+///     logging_timer::dump_recent_timers();
+///     default_hook(info);
+/// }));
+/// ```
+pub fn set_recent_timers_capacity(capacity: usize) {
+    CAPACITY.store(capacity, Ordering::Relaxed);
+    let mut buffer = buffer();
+    let excess = buffer.len().saturating_sub(capacity);
+    buffer.drain(..excess);
+    buffer.shrink_to(capacity);
+}
+
+/// Returns a copy of the timer messages currently in the buffer, oldest first.
+pub fn recent_timers() -> Vec<TimerRecord> {
+    buffer().iter().cloned().collect()
+}
+
+/// Writes the timer messages currently in the buffer to stderr, oldest first. They are
+/// written directly rather than logged, since the logger may be filtering timers out or
+/// may not be usable in a crash handler.
+pub fn dump_recent_timers() {
+    for record in buffer().iter() {
+        eprintln!(
+            "{} [{}] [{}:{}] {}",
+            record.level,
+            crate::format::target(record.target),
+            record.file,
+            record.line,
+            record.message
+        );
+    }
+}
+
+/// Returns true if the buffer is on.
+#[inline]
+pub(crate) fn is_active() -> bool {
+    CAPACITY.load(Ordering::Relaxed) > 0
+}
+
+pub(crate) fn push(record: TimerRecord) {
+    let capacity = CAPACITY.load(Ordering::Relaxed);
+    if capacity == 0 {
+        return;
+    }
+
+    let mut buffer = buffer();
+    while buffer.len() >= capacity {
+        buffer.pop_front();
+    }
+    buffer.push_back(record);
+}
@@ -0,0 +1,111 @@
+use crate::format::{FormattedDuration, separator};
+use crate::stats::TimerStats;
+use crate::{LoggingTimer, clock};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A timer handle that can be cloned into many places, such as one clone per item of a
+/// fan-out, where one summary line is wanted rather than a line per item. Each clone times
+/// itself from when it was made until it is dropped; the original handle, which only
+/// hands out the clones, is not counted. When the last handle is dropped the underlying
+/// timer logs a single 'finished' message giving the overall elapsed time and the count,
+/// total, mean, min and max of the clones, for example
+/// `FETCH_ALL, Elapsed=1.2s, Count=40, Total=9.6s, Mean=240ms, Min=80ms, Max=1.1s`.
+/// Create one with the `shared_timer!` macro.
+///
+/// ```norun
+/// let tmr = shared_timer!("FETCH_ALL");
+/// let handles: Vec<_> = urls
+///     .into_iter()
+///     .map(|url| {
+///         let tmr = tmr.clone();
+///         std::thread::spawn(move || {
+///             let _tmr = tmr;
+///             fetch(url)
+///         })
+///     })
+///     .collect();
+/// drop(tmr);
+/// ```
+pub struct SharedTimer<'name> {
+    shared: Arc<Shared<'name>>,
+    start_time: Instant,
+    // False for the original handle, whose elapsed time is not part of the summary.
+    is_clone: bool,
+}
+
+// The timer is only used once the last handle has gone, but is behind a mutex so that
+// the handles can be sent between threads even when `LoggingTimer` is not `Sync`.
+struct Shared<'name> {
+    enabled: bool,
+    timer: Mutex<Option<LoggingTimer<'name>>>,
+    stats: Mutex<Option<TimerStats>>,
+}
+
+impl<'name> SharedTimer<'name> {
+    /// Wraps a timer so that it can be shared by cloning.
+    /// This method is not usually called directly, use the `shared_timer!` macro instead.
+    pub fn new(timer: Option<LoggingTimer<'name>>) -> Self {
+        SharedTimer {
+            shared: Arc::new(Shared {
+                enabled: timer.is_some(),
+                timer: Mutex::new(timer),
+                stats: Mutex::new(None),
+            }),
+            start_time: clock::now(),
+            is_clone: false,
+        }
+    }
+
+    /// Returns how long this handle has been running for.
+    pub fn elapsed(&self) -> Duration {
+        clock::elapsed_since(self.start_time)
+    }
+}
+
+impl<'name> Clone for SharedTimer<'name> {
+    /// Makes another handle to the same timer, which times itself from now.
+    fn clone(&self) -> Self {
+        SharedTimer { shared: Arc::clone(&self.shared), start_time: clock::now(), is_clone: true }
+    }
+}
+
+impl<'name> Drop for SharedTimer<'name> {
+    /// Adds the elapsed time of this handle to the summary, if it is a clone.
+    fn drop(&mut self) {
+        if !self.is_clone || !self.shared.enabled {
+            return;
+        }
+
+        let elapsed = self.elapsed();
+        let mut stats = self.shared.stats.lock().unwrap_or_else(|e| e.into_inner());
+        match stats.as_mut() {
+            Some(stats) => stats.add(elapsed),
+            None => *stats = Some(TimerStats::new(elapsed)),
+        }
+    }
+}
+
+impl<'name> Drop for Shared<'name> {
+    /// Finishes the timer with the summary, once the last clone has been dropped.
+    fn drop(&mut self) {
+        let stats = self.stats.get_mut().unwrap_or_else(|e| e.into_inner());
+        let timer = self.timer.get_mut().unwrap_or_else(|e| e.into_inner());
+        if let (Some(tmr), Some(stats)) = (timer.as_ref(), stats.as_ref()) {
+            let sep = separator();
+            let format = tmr.duration_format();
+            tmr.finish(Some(format_args!(
+                "Count={}{}Total={}{}Mean={}{}Min={}{}Max={}",
+                stats.count(),
+                sep,
+                FormattedDuration(stats.total(), format),
+                sep,
+                FormattedDuration(stats.mean(), format),
+                sep,
+                FormattedDuration(stats.min(), format),
+                sep,
+                FormattedDuration(stats.max(), format)
+            )));
+        }
+    }
+}
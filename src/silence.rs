@@ -0,0 +1,31 @@
+use std::cell::Cell;
+
+thread_local! {
+    // The number of `SilenceGuard`s currently alive on this thread.
+    static SILENCED: Cell<u32> = const { Cell::new(0) };
+}
+
+/// While a value of this type is alive, no timer logs anything on the current thread.
+/// Create one with `silence_timers()`, or more conveniently use the `silence_timers!`
+/// macro. Guards can be nested; timers are heard again once they have all been dropped.
+pub struct SilenceGuard {
+    // Thread-local state, so the guard must not be sent to another thread.
+    _not_send: std::marker::PhantomData<*const ()>,
+}
+
+/// Silences all timers on the current thread until the returned guard is dropped.
+pub fn silence_timers() -> SilenceGuard {
+    SILENCED.with(|s| s.set(s.get() + 1));
+    SilenceGuard { _not_send: std::marker::PhantomData }
+}
+
+impl Drop for SilenceGuard {
+    fn drop(&mut self) {
+        SILENCED.with(|s| s.set(s.get() - 1));
+    }
+}
+
+/// Returns true if timers on the current thread are silenced.
+pub(crate) fn is_silenced() -> bool {
+    SILENCED.with(|s| s.get() > 0)
+}
@@ -0,0 +1,232 @@
+use crate::TimerName;
+use crate::format::FormattedDuration;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+// The interval between automatic dumps, in nanoseconds, 0 meaning never, and the
+// time of the last one.
+static DUMP_INTERVAL: AtomicU64 = AtomicU64::new(0);
+static LAST_DUMP: Mutex<Option<Instant>> = Mutex::new(None);
+
+// Timers named by strings and by ids are kept separately, so that recording a call
+// never needs to allocate once the name has been seen, and ids never need resolving.
+#[derive(Default)]
+struct Registry {
+    by_name: HashMap<String, TimerStats>,
+    by_id: HashMap<u32, TimerStats>,
+}
+
+/// A snapshot of the statistics recorded for one timer name, returned by `get_timer_stats`
+/// and `get_all_timer_stats`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TimerStats {
+    count: u64,
+    total: Duration,
+    min: Duration,
+    max: Duration,
+}
+
+impl TimerStats {
+    pub(crate) fn new(elapsed: Duration) -> Self {
+        TimerStats { count: 1, total: elapsed, min: elapsed, max: elapsed }
+    }
+
+    pub(crate) fn add(&mut self, elapsed: Duration) {
+        self.count += 1;
+        self.total += elapsed;
+        self.min = self.min.min(elapsed);
+        self.max = self.max.max(elapsed);
+    }
+
+    /// The number of calls.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The total elapsed time of all the calls.
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+
+    /// The mean elapsed time of a call.
+    pub fn mean(&self) -> Duration {
+        Duration::from_nanos((self.total.as_nanos() / self.count as u128) as u64)
+    }
+
+    /// The elapsed time of the fastest call.
+    pub fn min(&self) -> Duration {
+        self.min
+    }
+
+    /// The elapsed time of the slowest call.
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+}
+
+fn registry() -> MutexGuard<'static, Registry> {
+    static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Turns the aggregation of timer statistics on or off. When on, every timer that
+/// finishes records its elapsed time against its name, and `dump_timer_stats` can be
+/// called to log the count, total, mean, min and max for each name. All calls are
+/// counted, including those whose finished message is suppressed by a threshold.
+/// Timers are created regardless of their log level while this is on, so that they
+/// can be counted. The default is off.
+pub fn enable_timer_stats(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub(crate) fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Makes the timer statistics be dumped automatically, as if by `dump_timer_stats`, every
+/// `interval`, giving a periodic summary of all calls alongside the individual messages.
+/// The check is made as each timer finishes, so no thread is needed, but nothing is dumped
+/// while no timers are finishing. The statistics are cumulative, unless you also call
+/// `reset_timer_stats`. `None`, the default, turns the automatic dumps off.
+///
+/// Combined with a threshold this gives what profiling usually needs: the slow calls are
+/// logged individually as they happen, while every call, fast or slow, is counted in the
+/// periodic summary.
+///
+/// ```norun
+/// logging_timer::set_timer_threshold(Duration::from_millis(100));
+/// logging_timer::enable_timer_stats(true);
+/// logging_timer::set_timer_stats_dump_interval(Some(Duration::from_secs(60)));
+/// ```
+pub fn set_timer_stats_dump_interval(interval: Option<Duration>) {
+    let nanos = interval.map_or(0, |i| i.as_nanos().clamp(1, u64::MAX as u128) as u64);
+    DUMP_INTERVAL.store(nanos, Ordering::Relaxed);
+    *LAST_DUMP.lock().unwrap_or_else(|e| e.into_inner()) = Some(Instant::now());
+}
+
+// Dumps the statistics if the dump interval has passed since the last dump.
+fn dump_if_due() {
+    let interval = DUMP_INTERVAL.load(Ordering::Relaxed);
+    if interval == 0 || crate::deterministic::is_enabled() {
+        return;
+    }
+
+    let now = Instant::now();
+    {
+        let mut last_dump = LAST_DUMP.lock().unwrap_or_else(|e| e.into_inner());
+        match *last_dump {
+            Some(last) if now.duration_since(last) < Duration::from_nanos(interval) => return,
+            _ => *last_dump = Some(now),
+        }
+    }
+    dump_timer_stats();
+}
+
+/// Records a finished call of the named timer, if statistics are enabled.
+pub(crate) fn record(name: &TimerName, elapsed: Duration) {
+    if !is_enabled() {
+        return;
+    }
+
+    let mut registry = registry();
+    match name {
+        TimerName::Id(id) => match registry.by_id.get_mut(id) {
+            Some(stats) => stats.add(elapsed),
+            None => {
+                registry.by_id.insert(*id, TimerStats::new(elapsed));
+            }
+        },
+        TimerName::Str(name) => add_by_name(&mut registry, name, elapsed),
+        TimerName::Owned(name) => add_by_name(&mut registry, name, elapsed),
+    }
+    drop(registry);
+
+    dump_if_due();
+}
+
+fn add_by_name(registry: &mut Registry, name: &str, elapsed: Duration) {
+    match registry.by_name.get_mut(name) {
+        Some(stats) => stats.add(elapsed),
+        None => {
+            registry.by_name.insert(name.to_string(), TimerStats::new(elapsed));
+        }
+    }
+}
+
+/// Logs one message with a target of `TimerStats` for each timer name that has been
+/// recorded, stating the number of calls and their total, mean, min and max elapsed
+/// times. The messages are logged at Info level, in order of name.
+pub fn dump_timer_stats() {
+    for (name, stats) in get_all_timer_stats() {
+        emit_stats(&name, &stats);
+    }
+}
+
+/// Returns the statistics recorded so far for the timer called `name`, or `None` if no
+/// timer of that name has finished since the statistics were last reset. Timers named by
+/// id are found by the name registered for the id. This gives programmatic access to the
+/// same figures that `dump_timer_stats` logs, for example to serve them from your own
+/// endpoint or to assert on them in tests.
+///
+/// ```norun
+/// let stats = logging_timer::get_timer_stats("PARSE").unwrap();
+/// assert_eq!(stats.count(), 3);
+/// ```
+pub fn get_timer_stats(name: &str) -> Option<TimerStats> {
+    let registry = registry();
+    registry.by_name.get(name).copied().or_else(|| {
+        registry
+            .by_id
+            .iter()
+            .find(|(id, _)| TimerName::Id(**id).to_string() == name)
+            .map(|(_, stats)| *stats)
+    })
+}
+
+/// Returns the statistics recorded so far for every timer name, in order of name.
+pub fn get_all_timer_stats() -> Vec<(String, TimerStats)> {
+    let mut all: Vec<(String, TimerStats)> = {
+        let registry = registry();
+        let by_name = registry.by_name.iter().map(|(name, stats)| (name.clone(), *stats));
+        let by_id =
+            registry.by_id.iter().map(|(id, stats)| (TimerName::Id(*id).to_string(), *stats));
+        by_name.chain(by_id).collect()
+    };
+    all.sort_by(|a, b| a.0.cmp(&b.0));
+    all
+}
+
+/// Logs the statistics for one name, with a target of `TimerStats` at Info level.
+pub(crate) fn emit_stats(name: &str, stats: &TimerStats) {
+    let sep = crate::format::separator();
+    crate::emit(
+        &log::RecordBuilder::new()
+            .level(log::Level::Info)
+            .target("TimerStats")
+            .module_path(Some(module_path!()))
+            .args(format_args!(
+                "{}{}Count={}{}Total={}{}Mean={}{}Min={}{}Max={}",
+                name,
+                sep,
+                stats.count,
+                sep,
+                FormattedDuration(stats.total, None),
+                sep,
+                FormattedDuration(stats.mean(), None),
+                sep,
+                FormattedDuration(stats.min, None),
+                sep,
+                FormattedDuration(stats.max, None)
+            ))
+            .build(),
+    );
+}
+
+/// Discards all the statistics recorded so far.
+pub fn reset_timer_stats() {
+    *registry() = Registry::default();
+}
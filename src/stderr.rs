@@ -0,0 +1,112 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Setting this environment variable, to anything, stops the hint that no logger is
+/// installed from being written, for programs which run without a logger on purpose.
+const NO_LOGGER_HINT_VAR: &str = "LOGGING_TIMER_NO_LOGGER_HINT";
+
+static FALLBACK_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+// Set once the hint has been written, or a logger has been seen, after which there is
+// never any need to write it.
+static HINT_DONE: AtomicBool = AtomicBool::new(false);
+
+/// When set to true, and no logger has been installed, timers write their messages
+/// directly to stderr instead of discarding them. This is handy for quick debugging
+/// when you have not got round to configuring a logger such as `env_logger`. As soon
+/// as a real logger is installed, messages go to it instead. The default is false.
+///
+/// With the `color` feature the messages are colored according to their level, and the
+/// 'finished' messages of calls which reached the timer threshold are red, so that slow
+/// calls stand out.
+///
+/// Without a logger, and without this fallback, a hint saying that timer output is being
+/// discarded is written to stderr once, when a timer is created while there is no logger.
+/// Set the `LOGGING_TIMER_NO_LOGGER_HINT` environment variable to stop it.
+pub fn set_fallback_to_stderr(enabled: bool) {
+    FALLBACK_TO_STDERR.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns true if messages should be written to stderr rather than to the logger.
+pub(crate) fn fallback_active() -> bool {
+    FALLBACK_TO_STDERR.load(Ordering::Relaxed) && no_logger_installed()
+}
+
+/// Writes a hint to stderr, once, when a timer is found to be disabled because no logger
+/// has been installed, which is otherwise a puzzle for new users. Once a logger has been
+/// seen this does nothing, even if a later timer is disabled by its level.
+#[inline]
+pub(crate) fn hint_if_no_logger() {
+    if !HINT_DONE.load(Ordering::Relaxed) {
+        write_hint();
+    }
+}
+
+#[cold]
+fn write_hint() {
+    // Whatever the outcome, there is nothing to do next time: the hint has been written,
+    // or is not wanted, or a logger has been seen.
+    let suppressed = FALLBACK_TO_STDERR.load(Ordering::Relaxed)
+        || std::env::var_os(NO_LOGGER_HINT_VAR).is_some()
+        || !no_logger_installed();
+    if !HINT_DONE.swap(true, Ordering::Relaxed) && !suppressed {
+        eprintln!(
+            "logging_timer: no logger installed; timer output will be discarded. \
+             Set {} to hide this message.",
+            NO_LOGGER_HINT_VAR
+        );
+    }
+}
+
+// The log crate has no way of asking whether a logger has been installed, but when
+// there is none `log::logger()` returns its private zero-sized no-op logger and the
+// max level stays at its initial value of `Off`. Real loggers almost always have
+// some state, and always set the max level, otherwise they would never see anything.
+fn no_logger_installed() -> bool {
+    ::log::max_level() == ::log::LevelFilter::Off && std::mem::size_of_val(::log::logger()) == 0
+}
+
+pub(crate) fn write_record(record: &::log::Record, slow: bool) {
+    let (color, reset) = colors(record.level(), slow);
+    eprintln!(
+        "{}{} [{}] [{}/{}] {}{}",
+        color,
+        record.level(),
+        record.target(),
+        record.file().unwrap_or_default(),
+        record.line().unwrap_or_default(),
+        record.args(),
+        reset
+    );
+}
+
+/// With the `color` feature, returns the ANSI escape codes to color a line at `level`
+/// and to reset the color afterwards: red for errors, such as timers exceeding their
+/// maximum, and for `slow` calls over the threshold, yellow for warnings, such as slow
+/// calls escalated by level breakpoints, and so on. Colors are only used when stderr is
+/// a terminal and the `NO_COLOR` environment variable is not set.
+fn colors(level: ::log::Level, slow: bool) -> (&'static str, &'static str) {
+    #[cfg(feature = "color")]
+    {
+        use std::io::IsTerminal;
+        use std::sync::OnceLock;
+
+        static USE_COLOR: OnceLock<bool> = OnceLock::new();
+        let use_color = *USE_COLOR.get_or_init(|| {
+            std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+        });
+        if use_color {
+            let color = match level {
+                _ if slow => "\x1b[31m",
+                ::log::Level::Error => "\x1b[31m",
+                ::log::Level::Warn => "\x1b[33m",
+                ::log::Level::Info => "\x1b[32m",
+                ::log::Level::Debug => "\x1b[36m",
+                ::log::Level::Trace => "\x1b[90m",
+            };
+            return (color, "\x1b[0m");
+        }
+    }
+
+    let _ = (level, slow);
+    ("", "")
+}
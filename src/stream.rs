@@ -0,0 +1,140 @@
+use crate::LoggingTimer;
+use crate::format::{rate_precision, separator};
+use std::time::Duration;
+
+/// A timer for a streaming operation of known size, such as copying a large file, which
+/// periodically logs how far it has got. Create one with the `stream_timer!` macro and
+/// call `advance` as each chunk is processed; every so often an 'executing' message such
+/// as `COPY, Elapsed=2.5s, Progress=42.0%, Throughput=16.80MB/s, Eta=3.5s` is logged.
+/// When it is dropped the 'finished' message gives the total bytes and the overall
+/// throughput.
+///
+/// By default an update is logged at most once a second. Use `with_update_interval` and
+/// `with_update_every_bytes` to change the cadence; an update is logged when either of
+/// them is reached.
+///
+/// ```norun
+/// let mut tmr = stream_timer!("COPY", file_len);
+/// while let Some(chunk) = reader.next_chunk()? {
+///     writer.write_all(&chunk)?;
+///     tmr.advance(chunk.len() as u64);
+/// }
+/// ```
+pub struct StreamTimer<'name> {
+    timer: Option<LoggingTimer<'name>>,
+    total_bytes: u64,
+    bytes: u64,
+    update_interval: Option<Duration>,
+    update_every_bytes: Option<u64>,
+    // The elapsed time and byte count when the last update was logged.
+    last_update: (Duration, u64),
+}
+
+impl<'name> StreamTimer<'name> {
+    /// Wraps a timer to track the progress of an operation on `total_bytes` bytes.
+    /// This method is not usually called directly, use the `stream_timer!` macro instead.
+    pub fn new(timer: Option<LoggingTimer<'name>>, total_bytes: u64) -> Self {
+        StreamTimer {
+            timer,
+            total_bytes,
+            bytes: 0,
+            update_interval: Some(Duration::from_secs(1)),
+            update_every_bytes: None,
+            last_update: (Duration::ZERO, 0),
+        }
+    }
+
+    /// Logs an update when this much time has passed since the last one.
+    /// `None` means updates are not logged based on time.
+    pub fn with_update_interval(mut self, interval: Option<Duration>) -> Self {
+        self.update_interval = interval;
+        self
+    }
+
+    /// Logs an update when this many bytes have been processed since the last one.
+    /// `None`, the default, means updates are not logged based on bytes.
+    pub fn with_update_every_bytes(mut self, bytes: Option<u64>) -> Self {
+        self.update_every_bytes = bytes;
+        self
+    }
+
+    /// Records that another `bytes` bytes have been processed, logging an update if
+    /// one is due.
+    pub fn advance(&mut self, bytes: u64) {
+        self.bytes = self.bytes.saturating_add(bytes);
+
+        let tmr = match self.timer.as_ref() {
+            Some(tmr) => tmr,
+            None => return,
+        };
+
+        let elapsed = tmr.elapsed();
+        let (last_elapsed, last_bytes) = self.last_update;
+        let time_due =
+            self.update_interval.is_some_and(|i| elapsed.saturating_sub(last_elapsed) >= i);
+        let bytes_due = self.update_every_bytes.is_some_and(|b| self.bytes - last_bytes >= b);
+        if !time_due && !bytes_due {
+            return;
+        }
+        self.last_update = (elapsed, self.bytes);
+
+        let sep = separator();
+        let throughput = throughput(self.bytes, elapsed);
+        let progress = if self.total_bytes == 0 {
+            100.0
+        } else {
+            self.bytes as f64 * 100.0 / self.total_bytes as f64
+        };
+        let remaining = self.total_bytes.saturating_sub(self.bytes);
+
+        if throughput > 0.0 {
+            tmr.executing(Some(format_args!(
+                "Progress={:.1}%{}Throughput={:.*}MB/s{}Eta={:.1}s",
+                progress,
+                sep,
+                rate_precision(),
+                throughput,
+                sep,
+                remaining as f64 / (throughput * 1_000_000.0)
+            )));
+        } else {
+            tmr.executing(Some(format_args!(
+                "Progress={:.1}%{}Throughput={:.*}MB/s",
+                progress,
+                sep,
+                rate_precision(),
+                throughput
+            )));
+        }
+    }
+
+    /// Returns the number of bytes processed so far.
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Returns the underlying timer, if it is enabled.
+    pub fn timer(&self) -> Option<&LoggingTimer<'name>> {
+        self.timer.as_ref()
+    }
+}
+
+impl<'name> Drop for StreamTimer<'name> {
+    /// Finishes the timer, logging the total number of bytes and the overall throughput.
+    fn drop(&mut self) {
+        if let Some(tmr) = self.timer.as_ref() {
+            tmr.finish(Some(format_args!(
+                "Bytes={}{}Throughput={:.*}MB/s",
+                self.bytes,
+                separator(),
+                rate_precision(),
+                throughput(self.bytes, tmr.elapsed())
+            )));
+        }
+    }
+}
+
+// Megabytes (of one million bytes) per second.
+fn throughput(bytes: u64, elapsed: Duration) -> f64 {
+    bytes as f64 / 1_000_000.0 / elapsed.as_secs_f64().max(f64::MIN_POSITIVE)
+}
@@ -0,0 +1,69 @@
+use crate::{TimerRecord, TimerTarget};
+use std::cell::RefCell;
+
+thread_local! {
+    // The timer messages logged on this thread since the last clear.
+    static CAPTURED: RefCell<Vec<TimerRecord>> = const { RefCell::new(Vec::new()) };
+}
+
+struct TestLogger;
+
+impl log::Log for TestLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    // Printed with println! so that the test harness captures it along with the
+    // rest of the test's output.
+    fn log(&self, record: &log::Record) {
+        println!("{} [{}] {}", record.level(), record.target(), record.args());
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs a logger for use in tests, which enables all levels and prints every message
+/// to stdout, where the test harness captures it. Call it at the start of each test that
+/// uses `assert_timer_logged!`; calling it more than once is harmless. If another logger
+/// has already been installed, that one stays in place, and timers are only captured
+/// at the levels it enables.
+pub fn init_test_logger() {
+    let _ = log::set_logger(&TestLogger);
+    log::set_max_level(log::LevelFilter::Trace);
+}
+
+/// Returns the timer messages logged on the current thread since the thread started or
+/// `clear_captured_timers` was last called. Messages are captured per thread because the
+/// test harness runs each test on its own thread, so timers that finish on other threads
+/// are not seen by the test.
+pub fn captured_timers() -> Vec<TimerRecord> {
+    CAPTURED.with(|c| c.borrow().clone())
+}
+
+/// Discards the timer messages captured on the current thread.
+pub fn clear_captured_timers() {
+    CAPTURED.with(|c| c.borrow_mut().clear());
+}
+
+pub(crate) fn capture(record: TimerRecord) {
+    CAPTURED.with(|c| c.borrow_mut().push(record));
+}
+
+/// Implementation of `assert_timer_logged!`.
+#[doc(hidden)]
+#[track_caller]
+pub fn __assert_timer_logged(name: &str) {
+    let captured = captured_timers();
+    let found = captured.iter().any(|r| r.target == TimerTarget::Finished && r.name == name);
+    if !found {
+        let finished: Vec<&str> = captured
+            .iter()
+            .filter(|r| r.target == TimerTarget::Finished)
+            .map(|r| r.name.as_str())
+            .collect();
+        panic!(
+            "expected a timer named {:?} to have logged a finished message, but the finished timers were {:?}",
+            name, finished
+        );
+    }
+}
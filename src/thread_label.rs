@@ -0,0 +1,37 @@
+use std::cell::RefCell;
+use std::sync::Arc;
+
+thread_local! {
+    static LABEL: RefCell<Option<Arc<str>>> = const { RefCell::new(None) };
+}
+
+/// Sets a label, such as `worker-3`, which is included as a `Thread=` field in the messages
+/// of every timer subsequently created on the current thread. Set it once when the thread
+/// starts, to make concurrent timer output easier to read, for example in a worker pool
+/// where each thread has a distinct role. This is cheaper than looking up the thread's
+/// name for every timer, and allows threads to be labelled however you like.
+///
+/// # Examples
+///
+/// ```norun
+/// for i in 0..4 {
+///     std::thread::spawn(move || {
+///         logging_timer::set_thread_timer_label(format!("worker-{}", i));
+///         run_worker();
+///     });
+/// }
+/// ```
+pub fn set_thread_timer_label(label: impl Into<String>) {
+    let label: Arc<str> = Arc::from(label.into());
+    LABEL.with(|current| *current.borrow_mut() = Some(label));
+}
+
+/// Removes the label set by `set_thread_timer_label` from the current thread.
+pub fn clear_thread_timer_label() {
+    LABEL.with(|current| *current.borrow_mut() = None);
+}
+
+/// Returns the label of the current thread, if it has one.
+pub(crate) fn current() -> Option<Arc<str>> {
+    LABEL.with(|current| current.borrow().clone())
+}
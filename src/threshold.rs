@@ -0,0 +1,32 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+// In nanoseconds, 0 means no threshold.
+static THRESHOLD: AtomicU64 = AtomicU64::new(0);
+
+/// Sets a threshold below which timers do not log their 'finished' message, so that
+/// only the slow calls appear in the log. The calls are still counted in the timer
+/// statistics (see `enable_timer_stats`). A threshold can also be set on an individual
+/// timer using `LoggingTimer::with_threshold`, which takes precedence over this one.
+/// With the `ring-buffer` feature the suppressed messages are still kept in the buffer,
+/// so that `dump_recent_timers` shows the fast calls too. Pass `Duration::ZERO` to remove
+/// the threshold.
+pub fn set_timer_threshold(threshold: Duration) {
+    THRESHOLD.store(threshold.as_nanos() as u64, Ordering::Relaxed);
+}
+
+/// Returns true if a timer which finished after `elapsed` is fast enough that its
+/// 'finished' message should be suppressed.
+pub(crate) fn is_below(timer_threshold: Option<Duration>, elapsed: Duration) -> bool {
+    let threshold =
+        timer_threshold.unwrap_or_else(|| Duration::from_nanos(THRESHOLD.load(Ordering::Relaxed)));
+    elapsed < threshold
+}
+
+/// Returns true if there is a threshold, and a timer which finished after `elapsed`
+/// reached it.
+pub(crate) fn is_reached(timer_threshold: Option<Duration>, elapsed: Duration) -> bool {
+    let threshold =
+        timer_threshold.unwrap_or_else(|| Duration::from_nanos(THRESHOLD.load(Ordering::Relaxed)));
+    !threshold.is_zero() && elapsed >= threshold
+}
@@ -0,0 +1,26 @@
+use crate::LoggingTimer;
+use std::fmt;
+
+/// Implementation of `time_fn!`. Calls `body`, in which a `return` only leaves the
+/// closure, so the timer finishes once after the body however the body was left.
+#[doc(hidden)]
+pub fn __time_fn<T>(tmr: Option<LoggingTimer>, body: impl FnOnce() -> T) -> T {
+    let value = body();
+    drop(tmr);
+    value
+}
+
+/// Implementation of `time_fn!` with a `finish` closure. As `__time_fn`, but the timer
+/// is finished with the message `finish` makes from the value of the body.
+#[doc(hidden)]
+pub fn __time_fn_finish<T, D: fmt::Display>(
+    tmr: Option<LoggingTimer>,
+    body: impl FnOnce() -> T,
+    finish: impl FnOnce(&T) -> D,
+) -> T {
+    let value = body();
+    if let Some(tmr) = tmr {
+        tmr.finish(Some(format_args!("{}", finish(&value))));
+    }
+    value
+}
@@ -0,0 +1,96 @@
+use crate::LoggingTimer;
+use std::panic::Location;
+use std::time::Duration;
+
+/// A fixed set of named timers, declared up front and started and finished by name.
+/// This suits staged pipelines, where the same timers are used over and over and you
+/// want to control their lifecycle and level in one place.
+///
+/// Each timer is a normal `LoggingTimer` logging only a 'finished' message. Starting a
+/// timer which is already running finishes it first. Timers still running when the set
+/// is dropped are finished then. The source location logged is that of the `start` call.
+///
+/// # Examples
+///
+/// ```norun
+/// let mut timers = TimerSet::new(&["PARSE", "VALIDATE", "WRITE"], Level::Debug);
+/// for batch in batches {
+///     timers.start("PARSE");
+///     let parsed = parse(batch);
+///     timers.finish("PARSE");
+///
+///     timers.start("VALIDATE");
+///     // ...
+/// }
+/// ```
+pub struct TimerSet<'name> {
+    level: ::log::Level,
+    timers: Vec<(&'name str, Option<LoggingTimer<'name>>)>,
+}
+
+impl<'name> TimerSet<'name> {
+    /// Creates a set of timers with the given names, all logging at `level`.
+    /// None of the timers is started.
+    pub fn new(names: &[&'name str], level: ::log::Level) -> Self {
+        TimerSet { level, timers: names.iter().map(|name| (*name, None)).collect() }
+    }
+
+    /// Sets the level of the timers started from now on.
+    pub fn set_level(&mut self, level: ::log::Level) {
+        self.level = level;
+    }
+
+    /// Starts the named timer. Names which are not in the set are ignored, and
+    /// cause a panic in debug builds.
+    #[track_caller]
+    pub fn start(&mut self, name: &str) {
+        let level = self.level;
+        let location = Location::caller();
+        if let Some((name, timer)) = self.entry(name) {
+            // Finish any running timer before starting its replacement.
+            *timer = None;
+            *timer =
+                LoggingTimer::create(location.file(), None, location.line(), *name, None, level);
+        }
+    }
+
+    /// Finishes the named timer, if it is running. Names which are not in the set
+    /// are ignored, and cause a panic in debug builds.
+    pub fn finish(&mut self, name: &str) {
+        if let Some((_, timer)) = self.entry(name) {
+            if let Some(timer) = timer.take() {
+                timer.finish(None);
+            }
+        }
+    }
+
+    /// Finishes all the running timers.
+    pub fn finish_all(&mut self) {
+        for (_, timer) in self.timers.iter_mut() {
+            if let Some(timer) = timer.take() {
+                timer.finish(None);
+            }
+        }
+    }
+
+    /// Returns true if the named timer is running. This is always false if timers are
+    /// disabled at the set's level.
+    pub fn is_running(&self, name: &str) -> bool {
+        self.timers.iter().any(|(n, timer)| *n == name && timer.is_some())
+    }
+
+    /// Returns the elapsed time of the named timer, if it is running.
+    pub fn elapsed(&self, name: &str) -> Option<Duration> {
+        self.timers
+            .iter()
+            .find(|(n, _)| *n == name)
+            .and_then(|(_, timer)| timer.as_ref())
+            .map(LoggingTimer::elapsed)
+    }
+
+    fn entry(&mut self, name: &str) -> Option<&mut (&'name str, Option<LoggingTimer<'name>>)> {
+        let entry = self.timers.iter_mut().find(|(n, _)| *n == name);
+        debug_assert!(entry.is_some(), "{} is not in the TimerSet", name);
+        entry
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{LoggingTimer, TimerName, TimerOptions, clock, format, mute, operation};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A lightweight, `Send` record of a timer that has started, which can be handed to
+/// another thread to be finished there, for example when work is submitted on one thread
+/// and completed on another. It carries only the name, level, source location, extra
+/// information, operation id and start time; nothing is tracked while it is in flight.
+/// When it is finished, or dropped, it logs the same 'finished' message that a timer
+/// would, and is counted in the statistics in the same way. Create one with the
+/// `timer_token!` macro.
+///
+/// ```norun
+/// let token = timer_token!("JOB", "Id={}", job.id);
+/// let handle = std::thread::spawn(move || {
+///     run(job);
+///     if let Some(token) = token {
+///         token.finish(Some(format_args!("Done")));
+///     }
+/// });
+/// ```
+#[derive(Debug)]
+pub struct TimerToken {
+    level: ::log::Level,
+    file: &'static str,
+    module_path: &'static str,
+    line: u32,
+    start_time: Instant,
+    name: TimerName<'static>,
+    extra_info: Option<String>,
+    operation_id: Option<Arc<str>>,
+    finished: bool,
+}
+
+impl TimerToken {
+    /// Constructs a new `TimerToken`, starting it now.
+    /// This method is not usually called directly, use the `timer_token!` macro instead.
+    pub fn new(
+        file: &'static str,
+        module_path: &'static str,
+        line: u32,
+        name: impl Into<TimerName<'static>>,
+        extra_info: Option<String>,
+        level: ::log::Level,
+    ) -> Option<Self> {
+        let name = name.into();
+        if !LoggingTimer::is_enabled(level) || mute::is_muted(&name) {
+            return None;
+        }
+
+        Some(TimerToken {
+            level,
+            file,
+            module_path,
+            line,
+            start_time: clock::now(),
+            name,
+            extra_info: extra_info.map(format::truncate_extra_info),
+            operation_id: operation::current(),
+            finished: false,
+        })
+    }
+
+    /// Returns how long the token has been running for.
+    pub fn elapsed(&self) -> Duration {
+        clock::elapsed_since(self.start_time)
+    }
+
+    /// Returns the name of the token.
+    pub fn name(&self) -> &TimerName<'static> {
+        &self.name
+    }
+
+    /// Logs the 'finished' message, with further `format_args!` information if given.
+    pub fn finish(mut self, args: Option<fmt::Arguments>) {
+        self.finish_impl(args);
+    }
+
+    fn finish_impl(&mut self, args: Option<fmt::Arguments>) {
+        if std::mem::replace(&mut self.finished, true) {
+            return;
+        }
+
+        // A timer built from the token's parts, as if it had been running all along.
+        let tmr = LoggingTimer {
+            level: self.level,
+            file: self.file,
+            module_path: Some(self.module_path),
+            line: self.line,
+            finished: Default::default(),
+            start_time: self.start_time,
+            name: std::mem::replace(&mut self.name, TimerName::Str("")),
+            extra_info: self.extra_info.take(),
+            options: self.operation_id.take().map(|operation_id| {
+                Box::new(TimerOptions { operation_id: Some(operation_id), ..Default::default() })
+            }),
+        };
+        tmr.finish(args);
+    }
+}
+
+impl Drop for TimerToken {
+    /// Logs the 'finished' message if `finish` has not been called.
+    fn drop(&mut self) {
+        self.finish_impl(None);
+    }
+}
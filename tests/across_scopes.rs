@@ -0,0 +1,32 @@
+#![cfg(not(feature = "disabled"))]
+
+use logging_timer::{LoggingTimer, finish, timer};
+
+mod common;
+
+struct Job {
+    id: u32,
+    timer: Option<LoggingTimer<'static>>,
+}
+
+fn enqueue(id: u32) -> Job {
+    Job { id, timer: timer!(format!("JOB_{}", id)) }
+}
+
+fn dequeue(job: Job) {
+    finish!(job.timer, "Dequeued job {}", job.id);
+}
+
+#[test]
+fn timer_can_be_finished_in_a_different_function() {
+    common::init();
+
+    let job = enqueue(7);
+    assert!(common::take_messages().is_empty());
+
+    dequeue(job);
+    let messages = common::take_messages();
+    assert_eq!(messages.len(), 1);
+    assert!(messages[0].starts_with("DEBUG [TimerFinished] JOB_7, Elapsed="));
+    assert!(messages[0].ends_with(", Dequeued job 7"));
+}
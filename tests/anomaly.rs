@@ -0,0 +1,62 @@
+#![cfg(not(feature = "disabled"))]
+
+use logging_timer::{Clock, Level, set_anomaly_detection, set_clock, timer};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+mod common;
+
+/// A clock which only moves when the test advances it, so that every call takes
+/// exactly as long as the test says.
+struct ManualClock {
+    epoch: Instant,
+    now: Mutex<Duration>,
+}
+
+impl ManualClock {
+    fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap() += by;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.epoch + *self.now.lock().unwrap()
+    }
+}
+
+#[test]
+fn slow_call_is_reported_once_there_is_enough_history() {
+    let clock = Arc::new(ManualClock { epoch: Instant::now(), now: Mutex::new(Duration::ZERO) });
+    set_clock(clock.clone()).unwrap();
+    common::init();
+    set_anomaly_detection(Some(0.99));
+
+    let call = |elapsed: Duration| {
+        let _tmr = timer!(Level::Info; "QUERY");
+        clock.advance(elapsed);
+    };
+
+    // 100 calls of history, one of them far slower than the rest, which is not reported
+    // because there were too few calls before it.
+    for i in 0..100 {
+        call(Duration::from_millis(if i == 50 { 500 } else { 1 }));
+    }
+    let messages = common::take_messages();
+    assert_eq!(messages.len(), 100);
+    assert!(messages.iter().all(|m| m.starts_with("INFO [TimerFinished] QUERY, Elapsed=")));
+    assert!(messages.iter().all(|m| !m.contains("Anomaly=")), "{:?}", messages);
+
+    // Slower than 99% of the history, so reported at Warn level.
+    call(Duration::from_millis(200));
+    // No slower than the rest, so not.
+    call(Duration::from_millis(1));
+    set_anomaly_detection(None);
+
+    let messages = common::take_messages();
+    assert_eq!(messages.len(), 2);
+    assert!(messages[0].starts_with("WARN [TimerFinished] QUERY, Elapsed=200"), "{}", messages[0]);
+    assert!(messages[0].ends_with(", Anomaly=true"), "{}", messages[0]);
+    assert!(messages[1].starts_with("INFO [TimerFinished] QUERY, Elapsed=1"), "{}", messages[1]);
+    assert!(!messages[1].contains("Anomaly="), "{}", messages[1]);
+}
@@ -0,0 +1,61 @@
+#![cfg(all(feature = "chrome-trace", not(feature = "disabled")))]
+
+use logging_timer::{finish_chrome_trace, start_chrome_trace, timer};
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Clone, Default)]
+struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuf {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Returns the `ts` and `dur` of the complete event of the timer called `name`.
+fn event_times(trace: &str, name: &str) -> (f64, f64) {
+    let event = trace
+        .lines()
+        .find(|line| line.contains(&format!(r#""name":"{}""#, name)))
+        .unwrap_or_else(|| panic!("no event for {} in {}", name, trace));
+    assert!(event.contains(r#""ph":"X""#), "{}", event);
+    let number = |key: &str| -> f64 {
+        let start = event.find(&format!(r#""{}":"#, key)).unwrap() + key.len() + 3;
+        let end = event[start..].find([',', '}']).unwrap() + start;
+        event[start..end].parse().unwrap()
+    };
+    (number("ts"), number("dur"))
+}
+
+#[test]
+fn timers_finishing_out_of_order_have_the_right_times() {
+    let out = SharedBuf::default();
+    start_chrome_trace(out.clone()).unwrap();
+
+    let outer = timer!("OUTER");
+    std::thread::sleep(Duration::from_millis(5));
+    let inner = timer!("INNER");
+    std::thread::sleep(Duration::from_millis(5));
+    // The outer timer finishes first, which a pair of 'begin' and 'end' events would
+    // attribute to the inner one.
+    drop(outer);
+    std::thread::sleep(Duration::from_millis(5));
+    drop(inner);
+
+    finish_chrome_trace().unwrap();
+    let trace = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+
+    let (outer_ts, outer_dur) = event_times(&trace, "OUTER");
+    let (inner_ts, inner_dur) = event_times(&trace, "INNER");
+    assert!(outer_ts < inner_ts);
+    assert!(outer_ts + outer_dur < inner_ts + inner_dur);
+    assert!(outer_dur >= 10_000.0, "{}", outer_dur);
+    assert!(inner_dur >= 10_000.0, "{}", inner_dur);
+}
@@ -0,0 +1,16 @@
+use logging_timer::{BoottimeClock, Clock};
+use std::time::Duration;
+
+#[test]
+fn boottime_clock_advances() {
+    let boottime = BoottimeClock::new();
+    let b0 = boottime.now();
+
+    std::thread::sleep(Duration::from_millis(20));
+
+    // Boottime counts everything the monotonic clock does and more, so it has seen at
+    // least the time slept. There is no upper bound, since the machine may be loaded.
+    let b1 = boottime.now();
+    assert!(b1 >= b0 + Duration::from_millis(20), "{:?}", b1 - b0);
+    assert!(boottime.now() >= b1);
+}
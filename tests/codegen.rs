@@ -0,0 +1,46 @@
+#![cfg(all(feature = "test-util", not(feature = "disabled")))]
+
+//! Timers in generated code, as a build script would write it, report the location
+//! of the source it was generated from.
+
+use logging_timer::{TimerTarget, captured_timers, clear_captured_timers, init_test_logger};
+
+mod api {
+    include!("codegen/handlers.rs");
+}
+
+#[test]
+fn generated_timers_report_the_overridden_location() {
+    init_test_logger();
+    clear_captured_timers();
+
+    assert_eq!(api::get_user(7), 7);
+    assert_eq!(api::list_users().len(), 3);
+
+    let records = captured_timers();
+    assert_eq!(records.len(), 3);
+
+    assert_eq!(records[0].name, "GET_USER");
+    assert_eq!(
+        (records[0].file, records[0].module_path, records[0].line),
+        ("api.yaml", Some("api::get_user"), 12)
+    );
+
+    assert_eq!(records[1].target, TimerTarget::Starting);
+    assert_eq!(records[2].target, TimerTarget::Finished);
+    assert_eq!((records[2].file, records[2].line), ("api.yaml", 14));
+    assert!(records[2].message.ends_with("Route=/users"), "{}", records[2].message);
+}
+
+#[test]
+fn attributes_work_on_generated_functions() {
+    init_test_logger();
+    clear_captured_timers();
+
+    assert!(api::health());
+
+    let records = captured_timers();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].name, "api::health()");
+    assert!(records[0].file.ends_with("handlers.rs"), "{}", records[0].file);
+}
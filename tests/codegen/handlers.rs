@@ -0,0 +1,33 @@
+// This file stands in for the output of a build script, which would write it to
+// $OUT_DIR/handlers.rs from an API description such as this one:
+//
+//     10 | endpoints:
+//     11 |   - name: get_user
+//     12 |     route: /users/{id}
+//     13 |   - name: list_users
+//     14 |     route: /users
+//
+// Each handler's timer reports the location of its entry in api.yaml, not this file.
+
+pub fn get_user(id: u32) -> u32 {
+    let _tmr = logging_timer::timer_at!(
+        "api.yaml", "api::get_user", 12;
+        logging_timer::Level::Info;
+        "GET_USER"
+    );
+    id
+}
+
+pub fn list_users() -> Vec<u32> {
+    let _tmr = logging_timer::stimer_at!(
+        "api.yaml", "api::list_users", 14;
+        logging_timer::Level::Info;
+        "LIST_USERS", "Route={}", "/users"
+    );
+    vec![1, 2, 3]
+}
+
+#[logging_timer::time("info", "api::{}")]
+pub fn health() -> bool {
+    true
+}
@@ -0,0 +1,31 @@
+//! A logger which captures messages so that tests can make assertions about them.
+
+use std::sync::Mutex;
+
+static MESSAGES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+struct CapturingLogger;
+
+impl log::Log for CapturingLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        let message = format!("{} [{}] {}", record.level(), record.target(), record.args());
+        MESSAGES.lock().unwrap().push(message);
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs the capturing logger. Can be called more than once.
+pub fn init() {
+    let _ = log::set_logger(&CapturingLogger);
+    log::set_max_level(log::LevelFilter::Trace);
+}
+
+/// Returns the messages logged so far, and clears them.
+pub fn take_messages() -> Vec<String> {
+    std::mem::take(&mut *MESSAGES.lock().unwrap())
+}
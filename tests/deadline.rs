@@ -0,0 +1,44 @@
+#![cfg(all(feature = "deadline", not(feature = "disabled")))]
+
+use logging_timer::{Level, timer};
+use std::time::{Duration, Instant};
+
+mod common;
+
+// Waits up to a few seconds for the deadline thread to log a message containing `text`.
+fn wait_for(messages: &mut Vec<String>, text: &str) -> bool {
+    let give_up = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < give_up {
+        messages.extend(common::take_messages());
+        if messages.iter().any(|m| m.contains(text)) {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    false
+}
+
+// One test, as the captured messages are shared by every test in the binary.
+#[test]
+fn watcher_reports_expired_deadlines_of_running_timers_only() {
+    common::init();
+
+    // Reported by the watcher while the timer is still running, and not again when it
+    // finishes.
+    let slow = timer!(Level::Info; "SLOW"; deadline = Duration::from_millis(20));
+    let mut messages = Vec::new();
+    assert!(wait_for(&mut messages, "SLOW, Elapsed="), "{:?}", messages);
+    drop(slow);
+    messages.extend(common::take_messages());
+    assert_eq!(messages.len(), 2, "{:?}", messages);
+    assert!(messages[0].starts_with("WARN [TimerExecuting] SLOW, Elapsed="), "{}", messages[0]);
+    assert!(messages[0].ends_with(", DeadlineExceeded=20ms"), "{}", messages[0]);
+    assert!(messages[1].starts_with("INFO [TimerFinished] SLOW, Elapsed="), "{}", messages[1]);
+
+    // Finishing within the deadline means it is never reported.
+    drop(timer!(Level::Info; "FAST"; deadline = Duration::from_millis(20)));
+    std::thread::sleep(Duration::from_millis(100));
+    let messages = common::take_messages();
+    assert_eq!(messages.len(), 1, "{:?}", messages);
+    assert!(messages[0].starts_with("INFO [TimerFinished] FAST, Elapsed="), "{}", messages[0]);
+}
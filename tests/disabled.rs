@@ -0,0 +1,39 @@
+//! No logger is installed in this test binary, so every level is disabled.
+
+use logging_timer::{Level, LoggingTimer, stimer, timer, timer_id};
+
+// Evaluated by the compiler, which is what lets a compiled out timer fold away.
+const ERROR_COMPILED_OUT: bool = LoggingTimer::is_compiled_out(Level::Error);
+const TRACE_COMPILED_OUT: bool = LoggingTimer::is_compiled_out(Level::Trace);
+
+#[test]
+fn disabled_timer_is_none() {
+    assert!(timer!("DISABLED").is_none());
+    assert!(timer!(Level::Error; "DISABLED").is_none());
+    assert!(stimer!(Level::Trace; "DISABLED", "extra {}", 1).is_none());
+    assert!(timer_id!(42).is_none());
+}
+
+#[test]
+fn disabled_timer_does_not_evaluate_its_arguments() {
+    let mut name_evaluated = false;
+    let mut args_evaluated = false;
+    let tmr = timer!(
+        Level::Info;
+        { name_evaluated = true; "DISABLED" },
+        "{}",
+        { args_evaluated = true; 1 }
+    );
+
+    assert!(tmr.is_none());
+    assert!(!name_evaluated);
+    assert!(!args_evaluated);
+}
+
+#[test]
+fn compiled_out_follows_the_static_max_level() {
+    // Without the disabled feature nothing is compiled out, as log's default
+    // STATIC_MAX_LEVEL is Trace.
+    assert_eq!(ERROR_COMPILED_OUT, cfg!(feature = "disabled"));
+    assert_eq!(TRACE_COMPILED_OUT, cfg!(feature = "disabled"));
+}
@@ -0,0 +1,20 @@
+#![cfg(feature = "disabled")]
+
+use logging_timer::{Level, LoggingTimer, enable_timer_stats, stimer, timer};
+
+mod common;
+
+// Checked by the compiler: the disabled path of every timer macro is a constant `None`.
+const _: () = assert!(LoggingTimer::is_compiled_out(Level::Error));
+
+#[test]
+fn disabled_feature_compiles_timers_out() {
+    common::init();
+    enable_timer_stats(true);
+
+    let mut name_evaluated = false;
+    assert!(timer!(Level::Error; { name_evaluated = true; "DISABLED" }).is_none());
+    assert!(stimer!(Level::Trace; "DISABLED", "extra {}", 1).is_none());
+    assert!(!name_evaluated);
+    assert!(common::take_messages().is_empty());
+}
@@ -0,0 +1,33 @@
+#![cfg(not(feature = "disabled"))]
+
+//! The default duration format is part of the crate's output contract, so it is pinned
+//! here rather than compared with `Duration`'s `Debug` output, which may change.
+
+use logging_timer::{Level, log_timer_span};
+use std::time::{Duration, Instant};
+
+mod common;
+
+#[test]
+fn durations_are_written_exactly_in_the_largest_whole_unit() {
+    common::init();
+    let start = Instant::now();
+    let cases = [
+        (0, "0ns"),
+        (999, "999ns"),
+        (1_000, "1µs"),
+        (12_500, "12.5µs"),
+        (1_000_001, "1.000001ms"),
+        (28_835_275, "28.835275ms"),
+        (999_999_999, "999.999999ms"),
+        (1_000_000_000, "1s"),
+        (1_500_000_000, "1.5s"),
+        (61_000_000_001, "61.000000001s"),
+    ];
+
+    for (nanos, expected) in cases {
+        log_timer_span("D", start, start + Duration::from_nanos(nanos), Level::Info, None);
+        let messages = common::take_messages();
+        assert_eq!(messages, [format!("INFO [TimerFinished] D, Elapsed={}", expected)]);
+    }
+}
@@ -0,0 +1,49 @@
+#![cfg(feature = "kv")]
+
+use log::kv::{Error, Key, Value, VisitSource};
+use logging_timer::{Level, timer};
+use std::sync::Mutex;
+
+static FIELDS: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+struct KvLogger;
+
+struct Collect(Vec<(String, String)>);
+
+impl<'kvs> VisitSource<'kvs> for Collect {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), Error> {
+        let value = match value.to_u64() {
+            Some(n) => format!("u64:{}", n),
+            None => value.to_string(),
+        };
+        self.0.push((key.to_string(), value));
+        Ok(())
+    }
+}
+
+impl log::Log for KvLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        let mut collect = Collect(Vec::new());
+        record.key_values().visit(&mut collect).unwrap();
+        FIELDS.lock().unwrap().extend(collect.0);
+    }
+
+    fn flush(&self) {}
+}
+
+#[test]
+fn elapsed_is_a_numeric_field() {
+    log::set_logger(&KvLogger).unwrap();
+    log::set_max_level(log::LevelFilter::Trace);
+
+    drop(timer!(Level::Info; "KV"));
+
+    let fields = FIELDS.lock().unwrap();
+    assert_eq!(fields[0], ("timer".to_string(), "KV".to_string()));
+    assert_eq!(fields[1].0, "elapsed_ns");
+    assert!(fields[1].1.starts_with("u64:"), "{:?}", fields);
+}
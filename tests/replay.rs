@@ -0,0 +1,35 @@
+#![cfg(all(feature = "replay", feature = "serde", not(feature = "disabled")))]
+
+use logging_timer::{
+    Level, RecordedTimer, executing, finish_timer_recording, replay_timers, start_timer_recording,
+    stimer, timer,
+};
+
+mod common;
+
+#[test]
+fn serialized_recording_replays_the_original_messages() {
+    common::init();
+
+    start_timer_recording();
+    {
+        let tmr = stimer!(Level::Info; "IMPORT", "Dir={}", "/data");
+        executing!(tmr, "Files={}", 3);
+    }
+    {
+        let _tmr = timer!(Level::Trace; "QUIET");
+    }
+    let recording = finish_timer_recording();
+    let original = common::take_messages();
+    assert_eq!(recording.len(), 4);
+
+    let json = serde_json::to_string(&recording).unwrap();
+    let recording: Vec<RecordedTimer> = serde_json::from_str(&json).unwrap();
+    replay_timers(&recording);
+
+    let replayed = common::take_messages();
+    assert_eq!(replayed, original);
+    assert!(replayed[0].starts_with("INFO [TimerStarting] IMPORT"), "{}", replayed[0]);
+    assert!(replayed[1].ends_with(", Files=3"), "{}", replayed[1]);
+    assert!(replayed[3].starts_with("TRACE [TimerFinished] QUIET, Elapsed="), "{}", replayed[3]);
+}
@@ -0,0 +1,79 @@
+#![cfg(all(feature = "test-util", not(feature = "disabled")))]
+
+use logging_timer::{captured_timers, clear_captured_timers, init_test_logger, time};
+use std::collections::HashMap;
+
+#[time(result_len)]
+fn load_rows(n: u32) -> Vec<u32> {
+    (0..n).collect()
+}
+
+#[time(result_len)]
+fn index(words: &[&str]) -> HashMap<String, usize> {
+    if words.is_empty() {
+        return HashMap::new();
+    }
+    words.iter().enumerate().map(|(i, w)| (w.to_string(), i)).collect()
+}
+
+#[time(result, result_len)]
+fn parse_all(items: &[&str]) -> Result<Vec<u32>, std::num::ParseIntError> {
+    items.iter().map(|s| s.parse()).collect()
+}
+
+#[time(result)]
+fn evens(limit: &str) -> Result<impl Iterator<Item = u32>, std::num::ParseIntError> {
+    let limit: u32 = limit.parse()?;
+    if limit == 0 {
+        return Ok((0..0).step_by(2));
+    }
+    Ok((0..limit).step_by(2))
+}
+
+#[test]
+fn length_of_returned_collection_is_logged() {
+    init_test_logger();
+    clear_captured_timers();
+
+    assert_eq!(load_rows(3), vec![0, 1, 2]);
+    assert_eq!(index(&["a", "b"]).len(), 2);
+    assert!(index(&[]).is_empty());
+
+    let messages: Vec<_> = captured_timers().into_iter().map(|r| r.message).collect();
+    assert_eq!(messages.len(), 3);
+    assert!(messages[0].starts_with("load_rows(), Elapsed="), "{}", messages[0]);
+    assert!(messages[0].ends_with(", Len=3"), "{}", messages[0]);
+    assert!(messages[1].ends_with(", Len=2"), "{}", messages[1]);
+    assert!(messages[2].ends_with(", Len=0"), "{}", messages[2]);
+}
+
+#[test]
+fn length_of_ok_value_is_logged_with_result() {
+    init_test_logger();
+    clear_captured_timers();
+
+    assert_eq!(parse_all(&["1", "2"]), Ok(vec![1, 2]));
+    assert!(parse_all(&["x"]).is_err());
+
+    let messages: Vec<_> = captured_timers().into_iter().map(|r| r.message).collect();
+    assert_eq!(messages.len(), 2);
+    assert!(messages[0].ends_with(", Len=2"), "{}", messages[0]);
+    assert!(messages[1].contains(", Error=ParseIntError"), "{}", messages[1]);
+    assert!(!messages[1].contains("Len="), "{}", messages[1]);
+}
+
+#[test]
+fn impl_trait_result_keeps_return_and_question_mark() {
+    init_test_logger();
+    clear_captured_timers();
+
+    assert_eq!(evens("5").unwrap().collect::<Vec<_>>(), vec![0, 2, 4]);
+    assert_eq!(evens("0").unwrap().count(), 0);
+    assert!(evens("x").is_err());
+
+    let messages: Vec<_> = captured_timers().into_iter().map(|r| r.message).collect();
+    assert_eq!(messages.len(), 3);
+    assert!(!messages[0].contains("Error="), "{}", messages[0]);
+    assert!(!messages[1].contains("Error="), "{}", messages[1]);
+    assert!(messages[2].contains(", Error=ParseIntError"), "{}", messages[2]);
+}
@@ -0,0 +1,61 @@
+#![cfg(all(feature = "test-util", not(feature = "disabled")))]
+
+use logging_timer::{
+    Level, assert_timer_logged, captured_timers, clear_captured_timers, init_test_logger, time,
+    timer,
+};
+
+#[time]
+fn instrumented() {}
+
+// Upper-case names close to a level, or to "never", are not taken for misspelled levels,
+// which would be a warning, or an error with the `strict-levels` feature.
+#[time("SERVER")]
+fn server() {}
+
+#[time("ERRORS")]
+fn errors() {}
+
+#[time("INTO")]
+fn into() {}
+
+#[test]
+fn time_attribute_is_captured() {
+    init_test_logger();
+    instrumented();
+    assert_timer_logged!("instrumented()");
+}
+
+#[test]
+fn timer_macro_is_captured() {
+    init_test_logger();
+    {
+        let _tmr = timer!(Level::Trace; "LOAD", "{} items", 3);
+    }
+    assert_timer_logged!("LOAD");
+    assert_eq!(captured_timers().len(), 1);
+
+    clear_captured_timers();
+    assert!(captured_timers().is_empty());
+}
+
+#[test]
+#[should_panic(expected = "expected a timer named \"MISSING\"")]
+fn missing_timer_panics() {
+    init_test_logger();
+    let _tmr = timer!("PRESENT");
+    drop(_tmr);
+    assert_timer_logged!("MISSING");
+}
+
+#[test]
+fn upper_case_names_like_levels_are_names() {
+    init_test_logger();
+    clear_captured_timers();
+    server();
+    errors();
+    into();
+
+    let names: Vec<_> = captured_timers().into_iter().map(|r| r.name).collect();
+    assert_eq!(names, ["SERVER", "ERRORS", "INTO"]);
+}
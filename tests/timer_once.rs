@@ -0,0 +1,21 @@
+#![cfg(not(feature = "disabled"))]
+
+use logging_timer::{Level, timer_once};
+
+mod common;
+
+#[test]
+fn calls_before_the_logger_is_installed_do_not_use_up_the_first_call() {
+    let call = || timer_once!(Level::Info; "LOAD");
+
+    // No logger yet, so the level is filtered out and no timer is created.
+    assert!(call().is_none());
+
+    common::init();
+    assert!(call().is_some());
+    assert!(call().is_none());
+
+    let messages = common::take_messages();
+    assert_eq!(messages.len(), 1);
+    assert!(messages[0].starts_with("INFO [TimerFinished] LOAD, Elapsed="), "{}", messages[0]);
+}
@@ -0,0 +1,26 @@
+#![cfg(not(feature = "disabled"))]
+
+use logging_timer::{Level, timer_token};
+
+mod common;
+
+#[test]
+fn token_can_be_finished_on_another_thread() {
+    common::init();
+
+    let token = timer_token!(Level::Info; "SUBMITTED", "Id={}", 7);
+    assert!(token.is_some());
+
+    std::thread::spawn(move || {
+        if let Some(token) = token {
+            token.finish(Some(format_args!("Completed")));
+        }
+    })
+    .join()
+    .unwrap();
+
+    let messages = common::take_messages();
+    assert_eq!(messages.len(), 1);
+    assert!(messages[0].starts_with("INFO [TimerFinished] SUBMITTED, Elapsed="));
+    assert!(messages[0].ends_with(", Id=7, Completed"), "{}", messages[0]);
+}
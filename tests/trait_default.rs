@@ -0,0 +1,73 @@
+#![cfg(all(feature = "test-util", not(feature = "disabled")))]
+
+//! `#[time]` and `#[stime]` on the default methods of a trait instrument the default body,
+//! which every implementation that does not override the method shares.
+
+use logging_timer::{
+    TimerTarget, captured_timers, clear_captured_timers, init_test_logger, stime, time,
+};
+
+trait Shape {
+    fn sides(&self) -> u32;
+
+    #[time("info", "Shape::{}")]
+    fn describe(&self) -> String {
+        format!("{} sides", self.sides())
+    }
+
+    #[stime]
+    fn scaled<T>(&self, factor: T) -> u32
+    where
+        T: Into<u32>,
+        Self: Sized,
+    {
+        self.sides() * factor.into()
+    }
+}
+
+struct Triangle;
+
+impl Shape for Triangle {
+    fn sides(&self) -> u32 {
+        3
+    }
+}
+
+struct Square;
+
+impl Shape for Square {
+    fn sides(&self) -> u32 {
+        4
+    }
+
+    fn describe(&self) -> String {
+        "a square".to_string()
+    }
+}
+
+#[test]
+fn default_method_is_timed() {
+    init_test_logger();
+    clear_captured_timers();
+
+    assert_eq!(Triangle.describe(), "3 sides");
+    assert_eq!(Triangle.scaled(2u8), 6);
+
+    let records = captured_timers();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[0].name, "Shape::describe()");
+    assert_eq!(records[0].level, log::Level::Info);
+    assert_eq!(records[1].target, TimerTarget::Starting);
+    assert_eq!(records[2].name, "scaled()");
+    assert_eq!(records[2].target, TimerTarget::Finished);
+}
+
+#[test]
+fn overridden_method_is_not_timed() {
+    init_test_logger();
+    clear_captured_timers();
+
+    assert_eq!(Square.describe(), "a square");
+
+    assert!(captured_timers().is_empty());
+}